@@ -0,0 +1,41 @@
+use std::{
+    env,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Embeds the Git commit, build time, and target triple for `empd version`
+fn main() {
+    let git_commit = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()
+        .filter(|ou| ou.status.success())
+        .map(|ou| String::from_utf8_lossy(&ou.stdout).trim().to_owned())
+        .unwrap_or_default();
+
+    // `SOURCE_DATE_EPOCH` is set for reproducible builds
+    let build_timestamp = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|st| st.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0_u64, |du| du.as_secs())
+        });
+
+    let target = env::var("TARGET").unwrap_or_default();
+
+    println!("cargo:rustc-env=EMPD_GIT_COMMIT={git_commit}");
+    println!("cargo:rustc-env=EMPD_BUILD_TIMESTAMP={build_timestamp}");
+    println!("cargo:rustc-env=EMPD_TARGET={target}");
+
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    if Path::new(".git/HEAD").exists() {
+        println!("cargo:rerun-if-changed=.git/HEAD");
+        println!("cargo:rerun-if-changed=.git/refs");
+    }
+}
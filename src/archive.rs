@@ -0,0 +1,157 @@
+//! Inspection of zip and tar (optionally gzip-compressed) archives, which are useless if they contain
+//! no entries, or only empty files and directories
+
+use crate::{bold_if_greater_than_zero, exit_code::ExitCode, signal, CHECK_MARK, X};
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use owo_colors::OwoColorize;
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+};
+use tar::EntryType;
+use zip::ZipArchive;
+
+pub const GZIP_MAGIC: [u8; 2_usize] = [0x1f_u8, 0x8b_u8];
+const ZIP_MAGIC: [u8; 2_usize] = *b"PK";
+
+#[derive(Default)]
+struct EntryCounts {
+    directories: u32,
+    empty_files: u32,
+    non_empty_files: u32,
+    /// Symbolic links, hard links, devices, etc., which are never considered empty
+    other_entries: u32,
+}
+
+impl EntryCounts {
+    fn add_file(&mut self, size: u64) {
+        if size > 0_u64 {
+            self.non_empty_files += 1_u32;
+        } else {
+            self.empty_files += 1_u32;
+        }
+    }
+}
+
+/// Reports whether the archive at `path_str` contains no entries, only empty directories, or only
+/// empty files (and directories), all of which exit with code 0
+pub fn check(path_str: &str) -> anyhow::Result<Result<(), ExitCode>> {
+    let mut file = File::open(path_str).context("Could not open archive")?;
+
+    let mut magic = [0_u8; 2_usize];
+
+    let magic_len = file.read(&mut magic).context("Could not read archive")?;
+
+    file.seek(SeekFrom::Start(0_u64))
+        .context("Could not read archive")?;
+
+    let buf_reader = BufReader::new(file);
+
+    let entry_counts = match &magic[..magic_len] {
+        sl if sl == GZIP_MAGIC => count_tar_entries(GzDecoder::new(buf_reader))?,
+        sl if sl == ZIP_MAGIC => count_zip_entries(buf_reader)?,
+        _ => count_tar_entries(buf_reader)?,
+    };
+
+    let EntryCounts {
+        directories,
+        empty_files,
+        non_empty_files,
+        other_entries,
+    } = entry_counts;
+
+    let result = if non_empty_files > 0_u32 || other_entries > 0_u32 {
+        println!(
+            " {}  Archive \"{}\" is a {} (directories: {}, empty files: {}, non-empty files: {}, other entries: {})",
+            X.as_str().bold().red(),
+            path_str.bold(),
+            "non-empty archive".bold().red(),
+            bold_if_greater_than_zero(directories),
+            bold_if_greater_than_zero(empty_files),
+            bold_if_greater_than_zero(non_empty_files),
+            bold_if_greater_than_zero(other_entries)
+        );
+
+        Err(ExitCode::NonEmptyFile)
+    } else {
+        let description = match (directories, empty_files) {
+            (0_u32, 0_u32) => "no entries".to_owned(),
+            (_, 0_u32) => format!(
+                "only empty directories (directories: {})",
+                directories.bold()
+            ),
+            _ => format!(
+                "only empty files (directories: {}, empty files: {})",
+                bold_if_greater_than_zero(directories),
+                empty_files.bold()
+            ),
+        };
+
+        println!(
+            " {}  Archive \"{}\" contains {}",
+            CHECK_MARK.as_str().bold().green(),
+            path_str.bold(),
+            description
+        );
+
+        Ok(())
+    };
+
+    Ok(result)
+}
+
+fn count_tar_entries(reader: impl Read) -> anyhow::Result<EntryCounts> {
+    let mut archive = tar::Archive::new(reader);
+
+    let mut entry_counts = EntryCounts::default();
+
+    for re in archive.entries().context("Could not read tar archive")? {
+        if signal::received().is_some() {
+            break;
+        }
+
+        let entry = re.context("Could not read tar archive entry")?;
+
+        match entry.header().entry_type() {
+            EntryType::Directory => {
+                entry_counts.directories += 1_u32;
+            }
+            EntryType::Regular | EntryType::Continuous => {
+                entry_counts.add_file(entry.size());
+            }
+            _ => {
+                entry_counts.other_entries += 1_u32;
+            }
+        }
+    }
+
+    Ok(entry_counts)
+}
+
+fn count_zip_entries(reader: impl Read + Seek) -> anyhow::Result<EntryCounts> {
+    let mut archive = ZipArchive::new(reader).context("Could not read zip archive")?;
+
+    let mut entry_counts = EntryCounts::default();
+
+    for us in 0_usize..archive.len() {
+        if signal::received().is_some() {
+            break;
+        }
+
+        // Only the central directory is needed, so entries are never decompressed
+        let zip_file = archive
+            .by_index_raw(us)
+            .context("Could not read zip archive entry")?;
+
+        if zip_file.is_dir() {
+            entry_counts.directories += 1_u32;
+        } else if zip_file.is_symlink() {
+            entry_counts.other_entries += 1_u32;
+        } else {
+            entry_counts.add_file(zip_file.size());
+        }
+    }
+
+    Ok(entry_counts)
+}
@@ -0,0 +1,147 @@
+use crate::{
+    inspect::{Inspection, ItemKind, PathState},
+    schema,
+};
+use anyhow::Context;
+use serde::Serialize;
+use std::{
+    ffi::{CStr, CString},
+    fs::{File, OpenOptions},
+    io::Write,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::{self, Path},
+    time::SystemTime,
+};
+
+/// Append-only log with one JSON record per line for every deletion empd performs
+pub struct AuditLog {
+    file: File,
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    schema_version: u32,
+    timestamp: String,
+    user: Option<String>,
+    uid: u32,
+    /// Absolute, so that `empd restore` can recreate dangling symbolic links from any directory
+    path: String,
+    kind: ItemKind,
+    canonical_path: Option<&'a str>,
+    mode: Option<String>,
+    /// The owner and group of the path, as they were before it was deleted
+    owner_uid: Option<u32>,
+    owner_gid: Option<u32>,
+    /// When the path was last modified, in seconds since the Unix epoch
+    mtime: Option<i64>,
+    /// For symbolic links, so that `empd restore` can recreate them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<&'a str>,
+}
+
+impl AuditLog {
+    /// Opened up front so that an unwritable audit log is reported before anything is deleted
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .context("Could not open audit log")?;
+
+        Ok(Self { file })
+    }
+
+    pub fn record(&mut self, inspection: &Inspection) -> anyhow::Result<()> {
+        let uid = unsafe { libc::geteuid() };
+
+        let audit_record = AuditRecord {
+            schema_version: schema::SCHEMA_VERSION,
+            timestamp: format_timestamp(SystemTime::now())?,
+            user: user_name(uid),
+            uid,
+            path: path::absolute(&inspection.path)?
+                .to_str()
+                .context("Could not convert path to a UTF-8 string")?
+                .to_owned(),
+            kind: inspection
+                .kind()
+                .context("Cannot record deleting a path that could not be accessed")?,
+            canonical_path: inspection.canonical_path.as_deref(),
+            mode: inspection
+                .metadata
+                .as_ref()
+                .map(|me| format!("{:04o}", me.permissions().mode() & 0o7777)),
+            owner_uid: inspection.metadata.as_ref().map(MetadataExt::uid),
+            owner_gid: inspection.metadata.as_ref().map(MetadataExt::gid),
+            mtime: inspection.metadata.as_ref().map(MetadataExt::mtime),
+            target: match &inspection.state {
+                PathState::DanglingSymlink { target, .. } => Some(target),
+                _ => None,
+            },
+        };
+
+        let mut line = serde_json::to_string(&audit_record)?;
+
+        line.push('\n');
+
+        // A single write so that concurrent appenders do not interleave records
+        self.file
+            .write_all(line.as_bytes())
+            .context("Could not write to audit log")?;
+
+        Ok(())
+    }
+}
+
+/// Formats as RFC 3339 in UTC, e.g. "2024-10-15T08:30:00Z"
+pub fn format_timestamp(system_time: SystemTime) -> anyhow::Result<String> {
+    let seconds = system_time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .context("Timestamp is before the Unix epoch")?
+        .as_secs();
+
+    let time_t = libc::time_t::try_from(seconds)?;
+
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+
+    if unsafe { libc::gmtime_r(&raw const time_t, &raw mut tm) }.is_null() {
+        anyhow::bail!("Could not convert timestamp to UTC");
+    }
+
+    Ok(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        tm.tm_year + 1900_i32,
+        tm.tm_mon + 1_i32,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    ))
+}
+
+pub fn user_name(uid: u32) -> Option<String> {
+    let passwd = unsafe { libc::getpwuid(uid) };
+
+    if passwd.is_null() {
+        return None;
+    }
+
+    let name = unsafe { CStr::from_ptr((*passwd).pw_name) };
+
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// The user ID of `user`, which is a user name or, failing that, a numeric ID
+pub fn user_id(user: &str) -> anyhow::Result<u32> {
+    let c_string = CString::new(user)?;
+
+    let passwd = unsafe { libc::getpwnam(c_string.as_ptr()) };
+
+    if !passwd.is_null() {
+        return Ok(unsafe { (*passwd).pw_uid });
+    }
+
+    user.parse::<u32>()
+        .ok()
+        .with_context(|| format!("There is no user \"{user}\""))
+}
@@ -0,0 +1,115 @@
+//! A tar archive of everything deleted, with `--backup`, so that `empd restore` can put it back with
+//! its permissions, ownership, and modification time
+
+use crate::inspect::{Inspection, PathState};
+use anyhow::Context;
+use std::{
+    fs::OpenOptions,
+    io::{Read, Seek, SeekFrom},
+    path::{self, Path, PathBuf},
+};
+use tar::{Builder, EntryType, Header};
+
+/// A tar archive ends with two zero-filled 512-byte blocks
+const END_OF_ARCHIVE_BYTES: u64 = 1_024_u64;
+
+/// Adds `inspection`'s path to the archive at `backup_path`, creating it if needed. Called before
+/// deleting it, so that a failure to back it up leaves it in place.
+pub fn record(backup_path: &Path, inspection: &Inspection) -> anyhow::Result<()> {
+    let metadata = inspection
+        .metadata
+        .as_ref()
+        .context("Cannot back up a path without metadata")?;
+
+    let canonical_path = canonical_path(inspection)?;
+
+    // tar only allows relative paths, which are restored relative to "/", like GNU tar does
+    let archive_path = canonical_path.strip_prefix("/")?;
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(backup_path)
+        .context("Could not open backup archive")?;
+
+    let len = file.metadata()?.len();
+
+    // Overwrites the end of the archive, so that one archive collects every run's deletions
+    if len > 0_u64 {
+        let mut end = vec![0_u8; usize::try_from(END_OF_ARCHIVE_BYTES)?];
+
+        if len < END_OF_ARCHIVE_BYTES {
+            anyhow::bail!(
+                "Backup archive \"{}\" is not a tar archive",
+                backup_path.display()
+            );
+        }
+
+        file.seek(SeekFrom::Start(len - END_OF_ARCHIVE_BYTES))?;
+        file.read_exact(&mut end)?;
+
+        if end.iter().any(|ue| *ue != 0_u8) {
+            anyhow::bail!(
+                "Backup archive \"{}\" does not end like an archive written by empd",
+                backup_path.display()
+            );
+        }
+
+        file.seek(SeekFrom::Start(len - END_OF_ARCHIVE_BYTES))?;
+    }
+
+    let mut builder = Builder::new(file);
+
+    let mut header = Header::new_gnu();
+
+    header.set_metadata(metadata);
+    header.set_size(0_u64);
+
+    match &inspection.state {
+        PathState::EmptyDirectory => {
+            header.set_entry_type(EntryType::Directory);
+
+            builder.append_data(&mut header, archive_path, std::io::empty())?;
+        }
+        PathState::EmptyFile => {
+            header.set_entry_type(EntryType::Regular);
+
+            builder.append_data(&mut header, archive_path, std::io::empty())?;
+        }
+        PathState::DanglingSymlink { target, .. } => {
+            header.set_entry_type(EntryType::Symlink);
+
+            builder.append_link(&mut header, archive_path, target)?;
+        }
+        _ => anyhow::bail!("Only empty files, empty directories, and symbolic links are backed up"),
+    }
+
+    builder
+        .into_inner()
+        .context("Could not write to backup archive")?
+        .sync_all()
+        .context("Could not sync backup archive")?;
+
+    Ok(())
+}
+
+/// Canonical, as tar rejects ".." components
+fn canonical_path(inspection: &Inspection) -> anyhow::Result<PathBuf> {
+    if let Some(st) = &inspection.canonical_path {
+        return Ok(PathBuf::from(st));
+    }
+
+    // Dangling symbolic links have no canonical path, but their parent directory does
+    let absolute_path = path::absolute(&inspection.path)?;
+
+    let (Some(pa), Some(os)) = (absolute_path.parent(), absolute_path.file_name()) else {
+        anyhow::bail!("Could not determine the parent directory of the symbolic link");
+    };
+
+    Ok(pa
+        .canonicalize()
+        .context("Could not canonicalize the symbolic link's parent directory")?
+        .join(os))
+}
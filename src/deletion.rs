@@ -0,0 +1,469 @@
+use crate::{
+    audit::{self, AuditLog},
+    backup, exec,
+    exit_code::ExitCode,
+    file_system,
+    inspect::{self, InspectOptions, Inspection, ItemKind, PathState},
+    output::{self, note, status},
+    plan, signal,
+};
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, IsTerminal},
+    os::unix::fs::MetadataExt,
+    path::Path,
+    sync::Mutex,
+};
+
+/// What became of an empty path, reported in JSON so that scripts do not have to decode exit codes
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Deletion {
+    /// Deleting was not asked for, or the path was not an empty file, directory, or dangling symbolic link
+    NotAttempted,
+    Deleted,
+    /// The prompt was answered with something other than "y"
+    Declined,
+    /// The pre-delete hook exited unsuccessfully
+    Vetoed,
+    /// A signal arrived while waiting for an answer
+    Interrupted,
+    /// There was no terminal to ask on, and neither `--yes` nor `--no` was given
+    NotInteractive,
+    /// Recorded in the `--plan` file, to be deleted by `empd apply`
+    Planned,
+    /// The directory is a mount point, subvolume, or dataset, and deleting those was not allowed
+    Refused,
+    /// The path did not match `--owner`, `--writable-only`, or `--skip-in-use`, so it was left alone
+    /// without failing
+    Skipped,
+}
+
+#[allow(clippy::struct_excessive_bools)]
+pub struct DeleteOptions<'a> {
+    pub answers: Answers<'a>,
+    pub sync: bool,
+    pub pre_delete_hook: Option<&'a str>,
+    pub allow_mount_point: bool,
+    pub delete_subvolume: bool,
+    /// Only delete paths owned by this user ID
+    pub owner: Option<u32>,
+    /// Only delete paths that the invoking user can delete, rather than failing on the others
+    pub writable_only: bool,
+    /// Leave alone paths that a process has open, rather than racing with it
+    pub skip_in_use: bool,
+    /// Record deletions in this plan file for `empd apply` instead of prompting and deleting
+    pub plan: Option<&'a Path>,
+    /// Add everything deleted to this tar archive
+    pub backup: Option<&'a Path>,
+}
+
+/// How the confirmation prompts are answered
+#[derive(Clone, Copy, PartialEq)]
+pub enum Answers<'a> {
+    /// On stdin, if it and the prompt are on a terminal
+    Prompt,
+    /// "y" to every prompt, with `--yes`
+    Yes,
+    /// "n" to every prompt, with `--no`, so that everything up to deleting can be tried safely
+    No,
+    /// One line of this file per prompt, in order, with `--answers`
+    File(&'a Path),
+}
+
+/// The `--answers` file, opened at the first prompt and kept open across paths, so that each prompt
+/// gets the next line
+static ANSWERS_FILE: Mutex<Option<BufReader<File>>> = Mutex::new(None);
+
+/// The next line of the `--answers` file, or `None` if there are no lines left
+fn next_answer(path: &Path) -> anyhow::Result<Option<String>> {
+    let mut answers_file = ANSWERS_FILE
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Answers file lock was poisoned"))?;
+
+    if answers_file.is_none() {
+        let file = File::open(path)
+            .with_context(|| format!("Could not open answers file \"{}\"", path.display()))?;
+
+        *answers_file = Some(BufReader::new(file));
+    }
+
+    let mut line = String::new();
+
+    if let Some(bu) = answers_file.as_mut() {
+        bu.read_line(&mut line)
+            .context("Could not read from answers file")?;
+    }
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+
+    if !line.ends_with('\n') {
+        line.push('\n');
+    }
+
+    Ok(Some(line))
+}
+
+impl Deletion {
+    /// E.g. `Err(ExitCode::DirectoryDeletionDeclined)` for a declined directory deletion
+    pub fn result(self, inspection: &Inspection) -> Result<(), ExitCode> {
+        let Some(kind) = inspection.kind() else {
+            return Ok(());
+        };
+
+        let exit_code = match (self, kind) {
+            (Self::NotAttempted | Self::Deleted | Self::Skipped | Self::Planned, _) => {
+                return Ok(());
+            }
+            (Self::Declined, ItemKind::File) => ExitCode::FileDeletionDeclined,
+            (Self::Declined, ItemKind::Directory) => ExitCode::DirectoryDeletionDeclined,
+            (Self::Declined, ItemKind::Symlink) => ExitCode::SymlinkDeletionDeclined,
+            (Self::Vetoed, ItemKind::File) => ExitCode::FileDeletionVetoed,
+            (Self::Vetoed, ItemKind::Directory) => ExitCode::DirectoryDeletionVetoed,
+            (Self::Vetoed, ItemKind::Symlink) => ExitCode::SymlinkDeletionVetoed,
+            (Self::Interrupted, _) => ExitCode::Interrupted,
+            (Self::NotInteractive, ItemKind::File) => ExitCode::FileNotInteractive,
+            (Self::NotInteractive, ItemKind::Directory) => ExitCode::DirectoryNotInteractive,
+            (Self::NotInteractive, ItemKind::Symlink) => ExitCode::SymlinkNotInteractive,
+            // Only directories can be mount points or subvolumes
+            (Self::Refused, _) => ExitCode::DirectoryDeletionRefused,
+        };
+
+        Err(exit_code)
+    }
+}
+
+/// Deletes empty files, empty directories, and dangling symbolic links under one set of
+/// [`DeleteOptions`], recording each deletion in the audit log
+pub struct DeletionEngine<'a> {
+    delete_options: DeleteOptions<'a>,
+    audit_log: Option<AuditLog>,
+}
+
+/// What is about to be deleted, as it is described in prompts and messages
+struct Target {
+    kind: ItemKind,
+    /// E.g. "empty directory", or "semantically empty JSON file"
+    description: String,
+    prompt: String,
+}
+
+impl<'a> DeletionEngine<'a> {
+    pub fn new(delete_options: DeleteOptions<'a>, audit_log: Option<AuditLog>) -> Self {
+        Self {
+            delete_options,
+            audit_log,
+        }
+    }
+
+    /// Asks for confirmation, then deletes `inspection`'s path if it is empty and the pre-delete hook
+    /// (if any) allows it. `path_str` is the path shown in messages.
+    pub fn delete(&mut self, inspection: &Inspection, path_str: &str) -> anyhow::Result<Deletion> {
+        if let Some(de) = refusal(inspection, path_str, &self.delete_options) {
+            return Ok(de);
+        }
+
+        let Some(Target {
+            kind,
+            description,
+            prompt,
+        }) = target(inspection, path_str)
+        else {
+            return Ok(Deletion::NotAttempted);
+        };
+
+        if let Some(pa) = self.delete_options.plan {
+            plan::record(pa, inspection)?;
+
+            status!("Planned deleting {description} \"{}\"", path_str.bold());
+
+            return Ok(Deletion::Planned);
+        }
+
+        if let Some(de) = ask(self.delete_options.answers, &prompt, &description, path_str)? {
+            return Ok(de);
+        }
+
+        if let Some(st) = self.delete_options.pre_delete_hook {
+            if !exec::pre_delete_hook_allows(st, path_str, kind.as_str())? {
+                return Ok(Deletion::Vetoed);
+            }
+        }
+
+        let path_path = Path::new(&inspection.path);
+
+        if let Some(pa) = self.delete_options.backup {
+            backup::record(pa, inspection)?;
+        }
+
+        // TODO Status of path could have changed by now
+        match (kind, &inspection.subvolume) {
+            (ItemKind::Directory, Some(su)) => su.delete(path_path)?,
+            (ItemKind::Directory, None) => fs::remove_dir(path_path)?,
+            (ItemKind::File | ItemKind::Symlink, _) => fs::remove_file(path_path)?,
+        }
+
+        if self.delete_options.sync {
+            sync_parent_directory(path_path)?;
+        }
+
+        if let Some(au) = &mut self.audit_log {
+            au.record(inspection)?;
+        }
+
+        if kind == ItemKind::Symlink {
+            status!(
+                "Deleted symbolic link \"{}\" (non-canonicalized)",
+                path_str.bold()
+            );
+        } else {
+            status!("Deleted {description} \"{}\"", path_str.bold());
+        }
+
+        Ok(Deletion::Deleted)
+    }
+
+    /// For `--parents`: after `inspection`'s directory was deleted, deletes each ancestor in its path
+    /// (as given) that is now empty, stopping at the first that is not, like `rmdir -p`
+    pub fn delete_parents(
+        &mut self,
+        inspection: &Inspection,
+    ) -> anyhow::Result<Result<(), ExitCode>> {
+        if inspection.kind() != Some(ItemKind::Directory) {
+            return Ok(Ok(()));
+        }
+
+        let mut ancestors = Path::new(&inspection.path).ancestors().skip(1_usize);
+
+        while let Some(pa) = ancestors.next().filter(|pa| !pa.as_os_str().is_empty()) {
+            let parent_str = pa
+                .to_str()
+                .context("Could not convert path to a UTF-8 string")?;
+
+            let parent_inspection = inspect::inspect(parent_str, &InspectOptions::default())?;
+
+            if let PathState::NonEmptyDirectory(_) = parent_inspection.state {
+                note!(
+                    "Not deleting parent directory \"{}\", which is not empty",
+                    parent_str.bold()
+                );
+            }
+
+            let deletion = self.delete(&parent_inspection, parent_str)?;
+
+            if deletion != Deletion::Deleted {
+                return Ok(parent_inspection
+                    .result()
+                    .and_then(|()| deletion.result(&parent_inspection)));
+            }
+        }
+
+        Ok(Ok(()))
+    }
+}
+
+/// What the empty file, empty directory, or dangling symbolic link at `path_str` is, or `None` if it is
+/// none of those
+fn target(inspection: &Inspection, path_str: &str) -> Option<Target> {
+    let (kind, description, subject, caveat) = match &inspection.state {
+        PathState::EmptyDirectory => {
+            let description = match &inspection.subvolume {
+                Some(su) => format!("empty {}", su.description()),
+                None => "empty directory".to_owned(),
+            };
+
+            let subject = format!("{description} \"{}\"", path_str.bold());
+
+            (
+                ItemKind::Directory,
+                description,
+                subject,
+                "the directory may be non-empty",
+            )
+        }
+        PathState::EmptyFile => {
+            let description = "empty file".to_owned();
+
+            let subject = format!("{description} \"{}\"", path_str.bold());
+
+            (
+                ItemKind::File,
+                description,
+                subject,
+                "the file may be non-empty",
+            )
+        }
+        PathState::DanglingSymlink { target, .. } => {
+            let subject = format!(
+                "symbolic link \"{}\" (non-canonicalized) pointing to non-existent file \"{}\" (non-canonicalized)",
+                path_str.bold(),
+                target.bold()
+            );
+
+            (
+                ItemKind::Symlink,
+                "symbolic link".to_owned(),
+                subject,
+                "the symbolic link destination may exist",
+            )
+        }
+        _ => return None,
+    };
+
+    let prompt = format!(
+        "Are you sure you want to delete {subject}? (\"y\")\n\
+        (Note that no file locking or revalidation is performed, and {caveat} by the time you respond to this prompt!)"
+    );
+
+    Some(Target {
+        kind,
+        description,
+        prompt,
+    })
+}
+
+/// Asks `prompt` on the terminal, or answers it for `--yes`, `--no`, or `--answers`, returning `None` if
+/// the answer is "y", and otherwise what became of the path
+fn ask(
+    answers: Answers,
+    prompt: &str,
+    description: &str,
+    path_str: &str,
+) -> anyhow::Result<Option<Deletion>> {
+    let answer = match answers {
+        // Reading from a pipe or file that nobody is writing to would wait forever
+        Answers::Prompt if !io::stdin().is_terminal() || !output::notes_are_terminal() => {
+            note!(
+                "Not deleting {description} \"{}\", as there is no terminal to answer the prompt on (use --yes to delete without prompting)",
+                path_str.bold()
+            );
+
+            return Ok(Some(Deletion::NotInteractive));
+        }
+        Answers::Prompt => {
+            note!("{prompt}");
+
+            signal::read_line()?
+        }
+        Answers::Yes => Some("y\n".to_owned()),
+        Answers::No => {
+            note!("{prompt}\nAnswering \"n\" (--no)");
+
+            Some("n\n".to_owned())
+        }
+        Answers::File(pa) => {
+            let answer = next_answer(pa)?;
+
+            match &answer {
+                Some(st) => note!("{prompt}\nAnswering \"{}\" (--answers)", st.trim_end()),
+                None => note!("{prompt}\nNo answers left in \"{}\"", pa.display()),
+            }
+
+            // Running out of answers declines, like stdin ending
+            Some(answer.unwrap_or_default())
+        }
+    };
+
+    match answer {
+        None => Ok(Some(Deletion::Interrupted)),
+        Some(st) if st == "y\n" => Ok(None),
+        Some(_) => {
+            status!("Input was not \"y\", not deleting {description}");
+
+            Ok(Some(Deletion::Declined))
+        }
+    }
+}
+
+/// `Skipped` if `--owner`, `--writable-only`, or `--skip-in-use` rules out deleting the path, or `Refused` if it is a
+/// mount point, subvolume, or dataset that may not be deleted
+fn refusal(
+    inspection: &Inspection,
+    path_str: &str,
+    delete_options: &DeleteOptions,
+) -> Option<Deletion> {
+    if inspection.is_empty() {
+        if let Some(st) = skip_reason(inspection, delete_options) {
+            note!("Not deleting \"{}\", as {st}", path_str.bold());
+
+            return Some(Deletion::Skipped);
+        }
+    }
+
+    if let Some(su) = &inspection.subvolume {
+        if !delete_options.delete_subvolume {
+            note!(
+                "Not deleting \"{}\", as it is a {} (use --delete-subvolume to delete it anyway)",
+                path_str.bold(),
+                su.description()
+            );
+
+            return Some(Deletion::Refused);
+        }
+    }
+
+    // ZFS datasets are always mounted, so --delete-subvolume is enough for them
+    if inspection.mount_point.is_some()
+        && !delete_options.allow_mount_point
+        && !(delete_options.delete_subvolume && inspection.subvolume.is_some())
+    {
+        note!(
+            "Not deleting \"{}\", as a file system is mounted on it (use --allow-mount-point to delete it anyway)",
+            path_str.bold()
+        );
+
+        return Some(Deletion::Refused);
+    }
+
+    None
+}
+
+/// Why `--owner`, `--writable-only`, or `--skip-in-use` rules out deleting the path, if it does
+fn skip_reason(inspection: &Inspection, delete_options: &DeleteOptions) -> Option<String> {
+    let me = inspection.metadata.as_ref()?;
+
+    if let Some(ue) = delete_options.owner {
+        if me.uid() != ue {
+            let owner = audit::user_name(me.uid()).unwrap_or_else(|| me.uid().to_string());
+
+            return Some(format!("it is owned by {owner} (--owner)"));
+        }
+    }
+
+    if delete_options.writable_only && !file_system::can_delete(Path::new(&inspection.path), me) {
+        return Some("its directory does not allow you to delete it (--writable-only)".to_owned());
+    }
+
+    // Dangling symbolic links cannot be opened
+    if delete_options.skip_in_use && !me.is_symlink() {
+        if let Some(ue) = file_system::process_using(me) {
+            return Some(format!("process {ue} is using it (--skip-in-use)"));
+        }
+    }
+
+    None
+}
+
+fn sync_parent_directory(path_path: &Path) -> anyhow::Result<()> {
+    let parent = match path_path.parent() {
+        Some(pa) if !pa.as_os_str().is_empty() => pa,
+        _ => Path::new("."),
+    };
+
+    File::open(parent)
+        .context("Could not open parent directory")?
+        .sync_all()
+        .context("Could not fsync parent directory")?;
+
+    note!(
+        "Synced parent directory \"{}\"",
+        parent.to_string_lossy().bold()
+    );
+
+    Ok(())
+}
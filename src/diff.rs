@@ -0,0 +1,150 @@
+use crate::{exit_code::ExitCode, state::StateFile};
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use serde_json::Value;
+use std::{collections::BTreeSet, fs, path::Path};
+
+/// Summarizes which empty paths appeared and disappeared between two saved results, each either a
+/// `--state` file or a `--format sarif` log, compared as absolute paths
+pub fn diff(old_path: &Path, new_path: &Path) -> anyhow::Result<Result<(), ExitCode>> {
+    let old_empty_paths = read_empty_paths(old_path)?;
+    let new_empty_paths = read_empty_paths(new_path)?;
+
+    let appeared = new_empty_paths
+        .difference(&old_empty_paths)
+        .collect::<Vec<_>>();
+    let disappeared = old_empty_paths
+        .difference(&new_empty_paths)
+        .collect::<Vec<_>>();
+
+    for st in &appeared {
+        println!(" {}  {}", "+".bold().green(), st.bold());
+    }
+
+    for st in &disappeared {
+        println!(" {}  {}", "-".bold().red(), st.bold());
+    }
+
+    println!(
+        "Empty paths appeared: {}, disappeared: {}, unchanged: {}",
+        appeared.len().bold(),
+        disappeared.len().bold(),
+        new_empty_paths.intersection(&old_empty_paths).count()
+    );
+
+    Ok(Ok(()))
+}
+
+fn read_empty_paths(path: &Path) -> anyhow::Result<BTreeSet<String>> {
+    let string = fs::read_to_string(path)
+        .with_context(|| format!("Could not read \"{}\"", path.display()))?;
+
+    let value = serde_json::from_str::<Value>(&string)
+        .with_context(|| format!("Could not parse \"{}\"", path.display()))?;
+
+    // SARIF logs have "runs", state files have "paths"
+    if let Some(runs) = value.get("runs").and_then(Value::as_array) {
+        let empty_paths = runs
+            .iter()
+            .filter_map(|va| va.get("results").and_then(Value::as_array))
+            .flatten()
+            .filter_map(|va| {
+                va.pointer("/locations/0/physicalLocation/artifactLocation/uri")
+                    .and_then(Value::as_str)
+                    // Logs from before "file:" URIs hold percent-encoded paths
+                    .map(|st| percent_decode(st.strip_prefix("file://").unwrap_or(st)))
+            })
+            .map(absolute)
+            .collect();
+
+        return Ok(empty_paths);
+    }
+
+    let state_file = serde_json::from_value::<StateFile>(value).with_context(|| {
+        format!(
+            "\"{}\" is neither a state file nor a SARIF log",
+            path.display()
+        )
+    })?;
+
+    Ok(state_file
+        .paths
+        .into_iter()
+        .filter(|(_, pr)| pr.empty)
+        .map(|(st, _)| absolute(st))
+        .collect())
+}
+
+/// Older state files and SARIF logs can hold relative paths (e.g. of dangling symbolic links), which
+/// are resolved against the current directory as a best effort
+fn absolute(path_string: String) -> String {
+    let path = Path::new(&path_string);
+
+    if path.is_absolute() || path_string == "-" {
+        return path_string;
+    }
+
+    fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .map_or(path_string.clone(), |pa| pa.to_string_lossy().into_owned())
+}
+
+/// Undoes the percent-encoding of the path of a SARIF artifact URI, so that it compares equal to state
+/// file paths
+fn percent_decode(uri_reference: &str) -> String {
+    let bytes = uri_reference.as_bytes();
+
+    let mut decoded = Vec::<u8>::with_capacity(bytes.len());
+
+    let mut us = 0_usize;
+
+    while us < bytes.len() {
+        let hex = bytes
+            .get(us + 1_usize..us + 3_usize)
+            .and_then(|sl| std::str::from_utf8(sl).ok())
+            .and_then(|st| u8::from_str_radix(st, 16_u32).ok());
+
+        match (bytes[us], hex) {
+            (b'%', Some(ue)) => {
+                decoded.push(ue);
+
+                us += 3_usize;
+            }
+            (ue, _) => {
+                decoded.push(ue);
+
+                us += 1_usize;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decode_decodes_escapes() {
+        assert_eq!(percent_decode("/tmp/a%20b"), "/tmp/a b");
+        assert_eq!(percent_decode("%C3%A9"), "\u{e9}");
+    }
+
+    #[test]
+    fn percent_decode_keeps_invalid_escapes() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn absolute_keeps_absolute_paths_and_standard_input() {
+        assert_eq!(absolute("/nonexistent/a".to_owned()), "/nonexistent/a");
+        assert_eq!(absolute("-".to_owned()), "-");
+    }
+
+    #[test]
+    fn absolute_resolves_relative_paths() {
+        assert!(Path::new(&absolute("nonexistent".to_owned())).is_absolute());
+    }
+}
@@ -0,0 +1,61 @@
+//! Optional thousands separators for counts and byte sizes, which are hard to read on big trees
+
+use std::{
+    ffi::{CStr, CString},
+    sync::OnceLock,
+};
+
+static SEPARATOR: OnceLock<String> = OnceLock::new();
+
+/// Turns on grouping, with `separator`, or the current locale's (falling back to ",") if it is "locale"
+pub fn set(separator: &str) {
+    let separator = if separator == "locale" {
+        locale_separator().unwrap_or_else(|| ",".to_owned())
+    } else {
+        separator.to_owned()
+    };
+
+    SEPARATOR.get_or_init(|| separator);
+}
+
+/// E.g. "1234567" as "1,234,567" if grouping is on
+pub fn group(value: impl Into<u64>) -> String {
+    let digits = value.into().to_string();
+
+    let Some(separator) = SEPARATOR.get() else {
+        return digits;
+    };
+
+    let mut grouped = String::with_capacity(digits.len() * 2_usize);
+
+    for (us, ch) in digits.chars().enumerate() {
+        if us > 0_usize && (digits.len() - us) % 3_usize == 0_usize {
+            grouped.push_str(separator);
+        }
+
+        grouped.push(ch);
+    }
+
+    grouped
+}
+
+/// The thousands separator from `LC_ALL`, `LC_NUMERIC`, or `LANG`, if the locale has one (the "C" locale does not)
+fn locale_separator() -> Option<String> {
+    let empty = CString::default();
+
+    let thousands_sep = unsafe {
+        libc::setlocale(libc::LC_NUMERIC, empty.as_ptr());
+
+        let lconv = libc::localeconv();
+
+        if lconv.is_null() || (*lconv).thousands_sep.is_null() {
+            return None;
+        }
+
+        CStr::from_ptr((*lconv).thousands_sep)
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    (!thousands_sep.is_empty()).then_some(thousands_sep)
+}
@@ -0,0 +1,200 @@
+use crate::output::note;
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use std::process::{Command, Stdio};
+
+// Commands are run with `sh -c`, and "{}" is substituted with positional parameters rather than the
+// paths themselves, so that paths are never interpreted by the shell. The parameter is quoted to suit
+// where "{}" appears, so `{}`, `'{}'`, and `"{}"` (or "{}" inside a longer quoted string) all
+// expand to exactly one path.
+
+/// Runs `command` once for `path`, like `find -exec command {} \;`
+pub fn exec_for_finding(command: &str, path: &str) -> anyhow::Result<()> {
+    run(command, &substitute(command, '1'), &[path])
+}
+
+/// Runs `command` once for all of `paths`, like `find -exec command {} +`
+pub fn exec_for_findings(command: &str, paths: &[String]) -> anyhow::Result<()> {
+    let paths = paths.iter().map(String::as_str).collect::<Vec<_>>();
+
+    run(command, &substitute(command, '@'), &paths)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Quoting {
+    Unquoted,
+    Single,
+    Double,
+}
+
+/// Replaces every "{}" in `command` with the positional parameter `parameter` (`1` or `@`)
+fn substitute(command: &str, parameter: char) -> String {
+    // Command substitutions and subshells start afresh, e.g. `"$(wc -w < {})"`
+    let mut stack = vec![Quoting::Unquoted];
+
+    let mut script = String::with_capacity(command.len());
+
+    let mut chars = command.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        let quoting = stack.last().copied().unwrap_or(Quoting::Unquoted);
+
+        match (quoting, ch) {
+            (_, '{') if chars.peek() == Some(&'}') => {
+                chars.next();
+
+                let replacement = match quoting {
+                    Quoting::Unquoted => format!("\"${parameter}\""),
+                    // Closes the single-quoted string around the parameter, then reopens it
+                    Quoting::Single => format!("'\"${parameter}\"'"),
+                    Quoting::Double => format!("${parameter}"),
+                };
+
+                script.push_str(&replacement);
+
+                continue;
+            }
+            (Quoting::Single, '\'') | (Quoting::Double, '"') => {
+                stack.pop();
+            }
+            (Quoting::Single, _) => {}
+            (_, '\\') => {
+                script.push(ch);
+
+                if let Some(ne) = chars.next() {
+                    script.push(ne);
+                }
+
+                continue;
+            }
+            (Quoting::Unquoted, '\'') => stack.push(Quoting::Single),
+            (Quoting::Unquoted, '"') => stack.push(Quoting::Double),
+            (Quoting::Unquoted, '(') => stack.push(Quoting::Unquoted),
+            (Quoting::Unquoted, ')') if stack.len() > 1 => {
+                stack.pop();
+            }
+            (Quoting::Double, '$') if chars.peek() == Some(&'(') => {
+                chars.next();
+
+                script.push_str("$(");
+
+                stack.push(Quoting::Unquoted);
+
+                continue;
+            }
+            _ => {}
+        }
+
+        script.push(ch);
+    }
+
+    script
+}
+
+fn run(command: &str, script: &str, arguments: &[&str]) -> anyhow::Result<()> {
+    let exit_status = Command::new("sh")
+        .arg("-c")
+        .arg(script)
+        .arg("sh")
+        .args(arguments)
+        .status()
+        .with_context(|| format!("Could not run command \"{command}\""))?;
+
+    if !exit_status.success() {
+        note!(
+            "Command \"{}\" exited unsuccessfully ({exit_status})",
+            command.bold()
+        );
+    }
+
+    Ok(())
+}
+
+/// Runs the `--empty-when` `command` for `path`, returning whether it succeeded. Its stdout is
+/// discarded, so that it cannot get mixed into JSON output.
+pub fn predicate_holds(command: &str, path: &str) -> anyhow::Result<bool> {
+    let exit_status = Command::new("sh")
+        .arg("-c")
+        .arg(substitute(command, '1'))
+        .arg("sh")
+        .arg(path)
+        .stdout(Stdio::null())
+        .status()
+        .with_context(|| format!("Could not run command \"{command}\""))?;
+
+    Ok(exit_status.success())
+}
+
+/// Runs the pre-delete hook `command` for `path`, returning whether the deletion may proceed
+pub fn pre_delete_hook_allows(command: &str, path: &str, kind: &str) -> anyhow::Result<bool> {
+    let exit_status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("EMPD_PATH", path)
+        .env("EMPD_KIND", kind)
+        .status()
+        .with_context(|| format!("Could not run pre-delete hook \"{command}\""))?;
+
+    if !exit_status.success() {
+        note!(
+            "Pre-delete hook \"{}\" vetoed deleting \"{}\" ({exit_status})",
+            command.bold(),
+            path.bold()
+        );
+    }
+
+    Ok(exit_status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_bare_placeholders() {
+        assert_eq!(substitute("rm -- {}", '1'), r#"rm -- "$1""#);
+        assert_eq!(substitute("ls {}", '@'), r#"ls "$@""#);
+    }
+
+    #[test]
+    fn substitutes_quoted_placeholders() {
+        assert_eq!(substitute("rm -- '{}'", '1'), r#"rm -- ''"$1"''"#);
+        assert_eq!(substitute(r#"rm -- "{}""#, '1'), r#"rm -- "$1""#);
+        assert_eq!(
+            substitute("echo 'found: {}'", '1'),
+            r#"echo 'found: '"$1"''"#
+        );
+    }
+
+    #[test]
+    fn substitutes_placeholders_in_command_substitutions() {
+        assert_eq!(
+            substitute(r#"test "$(wc -w < {})" = 0"#, '1'),
+            r#"test "$(wc -w < "$1")" = 0"#
+        );
+    }
+
+    #[test]
+    fn leaves_escaped_characters_alone() {
+        assert_eq!(substitute(r"echo \' {}", '1'), r#"echo \' "$1""#);
+    }
+
+    #[test]
+    fn quoted_placeholders_expand_to_one_argument() {
+        for command in [
+            "printf '<%s>' {}",
+            "printf '<%s>' '{}'",
+            r#"printf '<%s>' "{}""#,
+        ] {
+            let output = Command::new("sh")
+                .arg("-c")
+                .arg(substitute(command, '1'))
+                .arg("sh")
+                .arg("a 'b\" c")
+                .output()
+                .unwrap();
+
+            assert_eq!(output.stdout, b"<a 'b\" c>");
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! The exit codes empd fails with, which are stable: a code's meaning never changes, and new
+//! outcomes get codes that have not been used before
+
+use crate::schema;
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::fmt;
+
+/// The tens digit is the kind of path (1 for any kind, 2 for files, 3 for directories, 4 for symbolic
+/// links), and the ones digit is what became of it
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(i32)]
+pub enum ExitCode {
+    Failure = 1_i32,
+    Empty = 10_i32,
+    NotFound = 11_i32,
+    PermissionDenied = 12_i32,
+    NonEmptyFile = 21_i32,
+    FileDeletionDeclined = 22_i32,
+    FileDeletionVetoed = 23_i32,
+    FileNotInteractive = 25_i32,
+    NonEmptyDirectory = 31_i32,
+    DirectoryDeletionDeclined = 32_i32,
+    DirectoryDeletionVetoed = 33_i32,
+    DirectoryDeletionRefused = 34_i32,
+    DirectoryNotInteractive = 35_i32,
+    Symlink = 41_i32,
+    SymlinkDeletionDeclined = 42_i32,
+    SymlinkDeletionVetoed = 43_i32,
+    SymlinkLoop = 44_i32,
+    SymlinkNotInteractive = 45_i32,
+    Interrupted = 130_i32,
+}
+
+impl ExitCode {
+    pub const ALL: [Self; 19_usize] = [
+        Self::Failure,
+        Self::Empty,
+        Self::NotFound,
+        Self::PermissionDenied,
+        Self::NonEmptyFile,
+        Self::FileDeletionDeclined,
+        Self::FileDeletionVetoed,
+        Self::FileNotInteractive,
+        Self::NonEmptyDirectory,
+        Self::DirectoryDeletionDeclined,
+        Self::DirectoryDeletionVetoed,
+        Self::DirectoryDeletionRefused,
+        Self::DirectoryNotInteractive,
+        Self::Symlink,
+        Self::SymlinkDeletionDeclined,
+        Self::SymlinkDeletionVetoed,
+        Self::SymlinkLoop,
+        Self::SymlinkNotInteractive,
+        Self::Interrupted,
+    ];
+
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Failure => "failure",
+            Self::Empty => "empty",
+            Self::NotFound => "not_found",
+            Self::PermissionDenied => "permission_denied",
+            Self::NonEmptyFile => "non_empty_file",
+            Self::FileDeletionDeclined => "file_deletion_declined",
+            Self::FileDeletionVetoed => "file_deletion_vetoed",
+            Self::FileNotInteractive => "file_not_interactive",
+            Self::NonEmptyDirectory => "non_empty_directory",
+            Self::DirectoryDeletionDeclined => "directory_deletion_declined",
+            Self::DirectoryDeletionVetoed => "directory_deletion_vetoed",
+            Self::DirectoryDeletionRefused => "directory_deletion_refused",
+            Self::DirectoryNotInteractive => "directory_not_interactive",
+            Self::Symlink => "symlink",
+            Self::SymlinkDeletionDeclined => "symlink_deletion_declined",
+            Self::SymlinkDeletionVetoed => "symlink_deletion_vetoed",
+            Self::SymlinkLoop => "symlink_loop",
+            Self::SymlinkNotInteractive => "symlink_not_interactive",
+            Self::Interrupted => "interrupted",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Failure => "An error occurred",
+            Self::Empty => "The path is empty, and --invert was used",
+            Self::NotFound => "The path does not exist",
+            Self::PermissionDenied => "The path could not be accessed",
+            Self::NonEmptyFile => "The path is a non-empty file (or an archive with entries)",
+            Self::FileDeletionDeclined => "Deleting the empty file was declined",
+            Self::FileDeletionVetoed => "The pre-delete hook vetoed deleting the empty file",
+            Self::FileNotInteractive => {
+                "The empty file was not deleted, as there was no terminal to prompt on"
+            }
+            Self::NonEmptyDirectory => "The path is a non-empty directory",
+            Self::DirectoryDeletionDeclined => "Deleting the empty directory was declined",
+            Self::DirectoryDeletionVetoed => {
+                "The pre-delete hook vetoed deleting the empty directory"
+            }
+            Self::DirectoryDeletionRefused => {
+                "The empty directory is a mount point, btrfs subvolume, or ZFS dataset, which was not deleted"
+            }
+            Self::DirectoryNotInteractive => {
+                "The empty directory was not deleted, as there was no terminal to prompt on"
+            }
+            Self::Symlink => "The path is a symbolic link to a path that exists",
+            Self::SymlinkDeletionDeclined => "Deleting the dangling symbolic link was declined",
+            Self::SymlinkDeletionVetoed => {
+                "The pre-delete hook vetoed deleting the dangling symbolic link"
+            }
+            Self::SymlinkLoop => "The path is a symbolic link that is part of a loop",
+            Self::SymlinkNotInteractive => {
+                "The dangling symbolic link was not deleted, as there was no terminal to prompt on"
+            }
+            Self::Interrupted => "Interrupted by SIGINT or SIGTERM",
+        }
+    }
+}
+
+impl fmt::Display for ExitCode {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.code().fmt(formatter)
+    }
+}
+
+#[derive(Serialize)]
+struct ExitCodeList {
+    schema_version: u32,
+    exit_codes: Vec<ExitCodeInfo>,
+}
+
+#[derive(Serialize)]
+struct ExitCodeInfo {
+    code: i32,
+    name: &'static str,
+    description: &'static str,
+}
+
+/// Lists every exit code, starting with 0
+pub fn print(json: bool) -> anyhow::Result<Result<(), ExitCode>> {
+    let success = ExitCodeInfo {
+        code: 0_i32,
+        name: "success",
+        description: "The path is empty (and was deleted, if deletion was requested)",
+    };
+
+    let exit_codes = std::iter::once(success)
+        .chain(ExitCode::ALL.iter().map(|ex| ExitCodeInfo {
+            code: ex.code(),
+            name: ex.name(),
+            description: ex.description(),
+        }))
+        .collect::<Vec<_>>();
+
+    if json {
+        let exit_code_list = ExitCodeList {
+            schema_version: schema::SCHEMA_VERSION,
+            exit_codes,
+        };
+
+        println!("{}", serde_json::to_string_pretty(&exit_code_list)?);
+
+        return Ok(Ok(()));
+    }
+
+    for ExitCodeInfo {
+        code,
+        name,
+        description,
+    } in exit_codes
+    {
+        println!("{:>3}  {}  {description}", code.bold(), name.dimmed());
+    }
+
+    Ok(Ok(()))
+}
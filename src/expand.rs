@@ -0,0 +1,125 @@
+use anyhow::Context;
+use std::{
+    env,
+    ffi::{CStr, CString},
+};
+
+/// Expands a leading "~" or "~user", and "$VAR" or "${VAR}" anywhere, as a shell would have if the
+/// path had not been quoted (cron and some shells pass these through literally). Unset variables and
+/// users that do not exist are kept as written, so that paths that only look like they need expanding
+/// are still checked.
+pub fn expand(path_str: &str) -> anyhow::Result<String> {
+    let path_str = expand_tilde(path_str)?;
+
+    let mut expanded = String::with_capacity(path_str.len());
+
+    let mut rest = path_str.as_str();
+
+    while let Some(us) = rest.find('$') {
+        expanded.push_str(&rest[..us]);
+
+        let after_dollar = &rest[us + 1_usize..];
+
+        let (name, after_name) = if let Some(st) = after_dollar.strip_prefix('{') {
+            let end = st
+                .find('}')
+                .with_context(|| format!("Path \"{path_str}\" has an unclosed \"${{\""))?;
+
+            (&st[..end], &st[end + 1_usize..])
+        } else {
+            let end = after_dollar
+                .find(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'))
+                .unwrap_or(after_dollar.len());
+
+            (&after_dollar[..end], &after_dollar[end..])
+        };
+
+        // A "$" that does not start a variable name is kept
+        if name.is_empty() || name.starts_with(|ch: char| ch.is_ascii_digit()) {
+            expanded.push('$');
+
+            rest = after_dollar;
+
+            continue;
+        }
+
+        match env::var(name) {
+            Ok(st) => expanded.push_str(&st),
+            Err(_) => expanded.push_str(&rest[us..rest.len() - after_name.len()]),
+        }
+
+        rest = after_name;
+    }
+
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+fn expand_tilde(path_str: &str) -> anyhow::Result<String> {
+    let Some(after_tilde) = path_str.strip_prefix('~') else {
+        return Ok(path_str.to_owned());
+    };
+
+    let (user, rest) = after_tilde.split_at(after_tilde.find('/').unwrap_or(after_tilde.len()));
+
+    let home = if user.is_empty() {
+        env::var("HOME").ok()
+    } else {
+        home_directory(user)?
+    };
+
+    Ok(match home {
+        Some(st) => format!("{st}{rest}"),
+        None => path_str.to_owned(),
+    })
+}
+
+/// `None` if there is no such user
+fn home_directory(user: &str) -> anyhow::Result<Option<String>> {
+    let c_string = CString::new(user)?;
+
+    let passwd = unsafe { libc::getpwnam(c_string.as_ptr()) };
+
+    if passwd.is_null() {
+        return Ok(None);
+    }
+
+    let home = unsafe { CStr::from_ptr((*passwd).pw_dir) };
+
+    Ok(Some(home.to_string_lossy().into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_expands_variables() {
+        let home = env::var("HOME").unwrap();
+
+        assert_eq!(expand("$HOME/a").unwrap(), format!("{home}/a"));
+        assert_eq!(expand("${HOME}b").unwrap(), format!("{home}b"));
+        assert_eq!(expand("~/a").unwrap(), format!("{home}/a"));
+    }
+
+    #[test]
+    fn expand_keeps_what_cannot_be_expanded() {
+        assert_eq!(expand("a$").unwrap(), "a$");
+        assert_eq!(expand("$1").unwrap(), "$1");
+        assert_eq!(
+            expand("a/$EMPD_TEST_UNSET/${EMPD_TEST_UNSET}").unwrap(),
+            "a/$EMPD_TEST_UNSET/${EMPD_TEST_UNSET}"
+        );
+        assert_eq!(
+            expand("~empd-test-no-such-user/a").unwrap(),
+            "~empd-test-no-such-user/a"
+        );
+        assert_eq!(expand("a~").unwrap(), "a~");
+    }
+
+    #[test]
+    fn expand_rejects_unclosed_braces() {
+        assert!(expand("${HOME").is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use anyhow::Context;
+use serde::Serialize;
+use std::{
+    ffi::{CString, OsString},
+    fs::{self, File, Metadata, OpenOptions},
+    io::{self, Write},
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::Path,
+    process::Command,
+};
+
+/// File systems whose files report a size of 0 even when reading them produces content
+const PSEUDO_FILE_SYSTEMS: [(libc::c_long, &str); 7_usize] = [
+    (0x0000_9fa0, "procfs"),
+    (0x6265_6572, "sysfs"),
+    (0x6462_6720, "debugfs"),
+    (0x7472_6163, "tracefs"),
+    (0x7363_6673, "securityfs"),
+    (0x6367_7270, "cgroup2"),
+    (0x6265_6570, "configfs"),
+];
+
+/// The `f_type` magic number `statfs` reports for the file system containing `path`
+pub fn magic(path: &Path) -> io::Result<libc::c_long> {
+    let c_string = CString::new(path.as_os_str().as_bytes()).map_err(io::Error::other)?;
+
+    let mut statfs = unsafe { std::mem::zeroed::<libc::statfs>() };
+
+    if unsafe { libc::statfs(c_string.as_ptr(), &raw mut statfs) } != 0_i32 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(statfs.f_type)
+}
+
+const BTRFS_MAGIC: libc::c_long = 0x9123_683e;
+
+/// The inode number of the root directory of every btrfs subvolume
+const BTRFS_FIRST_FREE_OBJECTID: u64 = 256_u64;
+
+/// A directory that is the root of a btrfs subvolume or a mounted ZFS dataset, which `rmdir` cannot
+/// delete
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum Subvolume {
+    BtrfsSubvolume,
+    ZfsDataset { dataset: String },
+}
+
+impl Subvolume {
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::BtrfsSubvolume => "btrfs subvolume",
+            Self::ZfsDataset { .. } => "ZFS dataset",
+        }
+    }
+
+    /// Deletes the subvolume or dataset with `btrfs subvolume delete` or `zfs destroy`
+    pub fn delete(&self, path: &Path) -> anyhow::Result<()> {
+        let mut command = match self {
+            Self::BtrfsSubvolume => {
+                let mut command = Command::new("btrfs");
+
+                command.args(["subvolume", "delete"]).arg(path);
+
+                command
+            }
+            Self::ZfsDataset { dataset } => {
+                let mut command = Command::new("zfs");
+
+                command.arg("destroy").arg(dataset);
+
+                command
+            }
+        };
+
+        let exit_status = command
+            .status()
+            .with_context(|| format!("Could not run {command:?}"))?;
+
+        if !exit_status.success() {
+            anyhow::bail!("{command:?} exited unsuccessfully ({exit_status})");
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the directory `path` is the root of a btrfs subvolume, or a ZFS dataset's mount point
+pub fn subvolume(path: &Path, mount_point: Option<&MountPoint>) -> Option<Subvolume> {
+    if let Some(mo) = mount_point {
+        if mo.file_system_type.as_deref() == Some("zfs") && mo.bind_of.is_none() {
+            return Some(Subvolume::ZfsDataset {
+                dataset: mo.source.clone()?,
+            });
+        }
+    }
+
+    let is_btrfs_subvolume = magic(path).ok()? == BTRFS_MAGIC
+        && fs::metadata(path).ok()?.ino() == BTRFS_FIRST_FREE_OBJECTID;
+
+    is_btrfs_subvolume.then_some(Subvolume::BtrfsSubvolume)
+}
+
+pub fn pseudo_file_system_name(magic: libc::c_long) -> Option<&'static str> {
+    PSEUDO_FILE_SYSTEMS
+        .iter()
+        .find(|(ma, _)| *ma == magic)
+        .map(|&(_, st)| st)
+}
+
+/// A file system mounted on a directory
+#[derive(Serialize)]
+pub struct MountPoint {
+    /// E.g. "ext4", if `/proc/self/mountinfo` could be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_system_type: Option<String>,
+    /// E.g. "/dev/sda1", if `/proc/self/mountinfo` could be read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// For bind mounts, the directory within `source` that is mounted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bind_of: Option<String>,
+}
+
+/// Whether `canonical_path` (a directory) is a mount point, from `/proc/self/mountinfo`, or if that
+/// cannot be read, from whether it is on a different device than its parent (which misses bind mounts
+/// within one file system)
+pub fn mount_point(canonical_path: &str) -> Option<MountPoint> {
+    if let Ok(st) = fs::read_to_string("/proc/self/mountinfo") {
+        // Later mounts on the same directory hide earlier ones
+        return st
+            .lines()
+            .filter_map(parse_mountinfo_line)
+            .rfind(|(mount_point, _)| mount_point == canonical_path)
+            .map(|(_, mo)| mo);
+    }
+
+    let path = Path::new(canonical_path);
+
+    let device = fs::metadata(path).ok()?.dev();
+
+    let parent_device = match path.parent() {
+        Some(pa) => fs::metadata(pa).ok()?.dev(),
+        // The root directory is always a mount point
+        None => u64::MAX,
+    };
+
+    (device != parent_device).then_some(MountPoint {
+        file_system_type: None,
+        source: None,
+        bind_of: None,
+    })
+}
+
+/// E.g. "36 35 98:0 /mnt1 /mnt/parent rw,noatime master:1 - ext3 /dev/root rw" is a bind mount of
+/// "/mnt1" on "/dev/root" at "/mnt/parent"
+fn parse_mountinfo_line(line: &str) -> Option<(String, MountPoint)> {
+    let (before_separator, after_separator) = line.split_once(" - ")?;
+
+    let mut fields = before_separator.split(' ');
+
+    let root = unescape_mountinfo(fields.nth(3_usize)?);
+    let mount_point = unescape_mountinfo(fields.next()?);
+
+    let mut fields = after_separator.split(' ');
+
+    let file_system_type = fields.next().map(unescape_mountinfo);
+    let source = fields.next().map(unescape_mountinfo);
+
+    let mount_point_value = MountPoint {
+        file_system_type,
+        source,
+        bind_of: (root != "/").then_some(root),
+    };
+
+    Some((mount_point, mount_point_value))
+}
+
+/// Spaces, tabs, newlines, and backslashes are escaped as octal, e.g. "\040" for a space
+fn unescape_mountinfo(field: &str) -> String {
+    let bytes = field.as_bytes();
+
+    let mut unescaped = Vec::<u8>::with_capacity(bytes.len());
+
+    let mut us = 0_usize;
+
+    while us < bytes.len() {
+        let octal = bytes
+            .get(us + 1_usize..us + 4_usize)
+            .and_then(|sl| std::str::from_utf8(sl).ok())
+            .and_then(|st| u8::from_str_radix(st, 8_u32).ok());
+
+        match (bytes[us], octal) {
+            (b'\\', Some(ue)) => {
+                unescaped.push(ue);
+
+                us += 4_usize;
+            }
+            (ue, _) => {
+                unescaped.push(ue);
+
+                us += 1_usize;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Whether the invoking user can delete the entry `path` (with `symlink_metadata` `metadata`): its
+/// directory must be writable and searchable, and if that directory is sticky (like /tmp), the user
+/// must own the entry or the directory
+pub fn can_delete(path: &Path, metadata: &Metadata) -> bool {
+    let parent = match path.parent() {
+        Some(pa) if !pa.as_os_str().is_empty() => pa,
+        _ => Path::new("."),
+    };
+
+    let Ok(c_string) = CString::new(parent.as_os_str().as_bytes()) else {
+        return false;
+    };
+
+    if unsafe { libc::access(c_string.as_ptr(), libc::W_OK | libc::X_OK) } != 0_i32 {
+        return false;
+    }
+
+    let Ok(parent_metadata) = fs::metadata(parent) else {
+        return false;
+    };
+
+    let euid = unsafe { libc::geteuid() };
+
+    parent_metadata.mode() & libc::S_ISVTX == 0_u32
+        || euid == 0_u32
+        || metadata.uid() == euid
+        || parent_metadata.uid() == euid
+}
+
+/// The ID of a process that has the file or directory with `metadata` open, or as its working directory,
+/// from `/proc/*/fd` and `/proc/*/cwd` (only other users' processes that are visible are checked)
+pub fn process_using(metadata: &Metadata) -> Option<u32> {
+    let is_same = |path: &Path| {
+        fs::metadata(path).is_ok_and(|me| me.dev() == metadata.dev() && me.ino() == metadata.ino())
+    };
+
+    let own_id = std::process::id();
+
+    fs::read_dir("/proc")
+        .ok()?
+        .filter_map(Result::ok)
+        .filter_map(|di| {
+            let process_id = di.file_name().to_str()?.parse::<u32>().ok()?;
+
+            (process_id != own_id).then_some((process_id, di.path()))
+        })
+        .find(|(_, pa)| {
+            is_same(&pa.join("cwd"))
+                || fs::read_dir(pa.join("fd"))
+                    .is_ok_and(|re| re.filter_map(Result::ok).any(|di| is_same(&di.path())))
+        })
+        .map(|(ue, _)| ue)
+}
+
+/// Writes to a temporary file next to `path` and renames it over `path`, so that readers never see a
+/// partial file and an interrupted write or crash leaves the previous contents
+pub fn write_atomically(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let parent = match path.parent() {
+        Some(pa) if !pa.as_os_str().is_empty() => pa,
+        _ => Path::new("."),
+    };
+
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("\"{}\" does not name a file", path.display()))?;
+
+    // Unique per process and attempt, and created exclusively, so that concurrent writers never share
+    // a temporary file and an existing file (or symbolic link) of that name is never written through
+    let mut attempt = 0_u32;
+
+    let (temporary_path, mut file) = loop {
+        let mut temporary_name = OsString::from(".");
+
+        temporary_name.push(file_name);
+        temporary_name.push(format!(".{}.{attempt}.tmp", std::process::id()));
+
+        let temporary_path = parent.join(temporary_name);
+
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temporary_path)
+        {
+            Ok(fi) => break (temporary_path, fi),
+            Err(er) if er.kind() == io::ErrorKind::AlreadyExists => attempt += 1_u32,
+            Err(er) => {
+                return Err(er).with_context(|| {
+                    format!(
+                        "Could not create a temporary file for \"{}\"",
+                        path.display()
+                    )
+                });
+            }
+        }
+    };
+
+    let result = file
+        .write_all(contents)
+        .and_then(|()| file.sync_all())
+        .with_context(|| format!("Could not write \"{}\"", path.display()))
+        .and_then(|()| {
+            fs::rename(&temporary_path, path)
+                .with_context(|| format!("Could not replace \"{}\"", path.display()))
+        });
+
+    if let Err(er) = result {
+        let _ = fs::remove_file(&temporary_path);
+
+        return Err(er);
+    }
+
+    // The rename is only durable once the directory entry is
+    File::open(parent)
+        .and_then(|fi| fi.sync_all())
+        .with_context(|| format!("Could not fsync \"{}\"", parent.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescape_mountinfo_decodes_octal_escapes() {
+        assert_eq!(unescape_mountinfo("/mnt/My\\040Drive"), "/mnt/My Drive");
+        assert_eq!(unescape_mountinfo("a\\011b\\012c\\134d"), "a\tb\nc\\d");
+    }
+
+    #[test]
+    fn unescape_mountinfo_keeps_other_backslashes() {
+        assert_eq!(unescape_mountinfo("/mnt/plain"), "/mnt/plain");
+        assert_eq!(unescape_mountinfo("a\\9b\\"), "a\\9b\\");
+    }
+
+    #[test]
+    fn write_atomically_replaces_contents_without_leaving_temporary_files() {
+        let directory = std::env::temp_dir().join(format!("empd-write-{}", std::process::id()));
+
+        fs::create_dir_all(&directory).unwrap();
+
+        let path = directory.join("state.json");
+
+        write_atomically(&path, b"old").unwrap();
+        write_atomically(&path, b"new").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 1_usize);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
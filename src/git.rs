@@ -0,0 +1,287 @@
+//! Checks of Git repositories through the `git` command, which reads the index and object database
+//! so that empd does not have to
+
+use crate::{exit_code::ExitCode, output::note, signal, X};
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use std::{collections::BTreeMap, ffi::OsStr, path::Path, process::Command};
+
+/// Object IDs of the empty blob in SHA-1 and SHA-256 repositories
+const EMPTY_BLOB_IDS: [&str; 2_usize] = [
+    "e69de29bb2d1d6434b8b29ae775ad8c2e48c5391",
+    "473a0f4c3be8a93681a267e3b1e9a7dcda1185436fe141f7749120a303721813",
+];
+
+const REGULAR_FILE_MODES: [&str; 2_usize] = ["100644", "100755"];
+
+/// An entry of the index, as listed by `git ls-files --stage`
+struct IndexEntry {
+    mode: String,
+    object: String,
+    path: String,
+}
+
+impl IndexEntry {
+    fn is_empty_file(&self) -> bool {
+        REGULAR_FILE_MODES.contains(&self.mode.as_str())
+            && EMPTY_BLOB_IDS.contains(&self.object.as_str())
+    }
+}
+
+/// Reports tracked zero-byte files, and the outermost tracked directories that contain nothing but
+/// zero-byte files (e.g. ".gitkeep" placeholders), with the last commit that touched each
+pub fn scan(directory: &str) -> anyhow::Result<Result<(), ExitCode>> {
+    let index_entries =
+        parse_index_entries(&git_output(directory, &["ls-files", "--stage", "-z"])?)?;
+
+    // Whether each directory contains only zero-byte files
+    let mut directories = BTreeMap::<String, bool>::new();
+
+    for index_entry in &index_entries {
+        let is_empty_file = index_entry.is_empty_file();
+
+        let mut ancestor = Path::new(&index_entry.path).parent();
+
+        while let Some(pa) = ancestor {
+            if pa.as_os_str().is_empty() {
+                break;
+            }
+
+            let only_empty_files = directories
+                .entry(pa.to_string_lossy().into_owned())
+                .or_insert(true);
+
+            *only_empty_files &= is_empty_file;
+
+            ancestor = pa.parent();
+        }
+    }
+
+    let placeholder_directories = directories
+        .iter()
+        .filter(|&(st, &bo)| {
+            let parent_has_only_empty_files = Path::new(st)
+                .parent()
+                .and_then(|pa| directories.get(pa.to_string_lossy().as_ref()))
+                .copied()
+                .unwrap_or(false);
+
+            bo && !parent_has_only_empty_files
+        })
+        .map(|(st, _)| st.as_str())
+        .collect::<Vec<_>>();
+
+    let empty_files = index_entries
+        .iter()
+        .filter(|ie| ie.is_empty_file())
+        .map(|ie| ie.path.as_str())
+        .collect::<Vec<_>>();
+
+    for (description, paths) in [
+        ("Tracked empty file", &empty_files),
+        (
+            "Tracked directory with only empty files",
+            &placeholder_directories,
+        ),
+    ] {
+        for st in paths {
+            // Each finding runs `git log`, which is where large repositories spend their time
+            if signal::received().is_some() {
+                return Ok(Err(ExitCode::Interrupted));
+            }
+
+            println!(
+                "{description} \"{}\" ({})",
+                st.bold(),
+                last_commit(directory, st)?
+            );
+        }
+    }
+
+    println!(
+        "Found {} tracked empty files and {} tracked directories with only empty files",
+        empty_files.len().bold(),
+        placeholder_directories.len().bold()
+    );
+
+    Ok(Ok(()))
+}
+
+/// Fails if the staged changes add empty files or empty out existing ones, other than placeholders
+/// named in `allowed_placeholders` or the `empd.placeholder` Git configuration (e.g. ".gitkeep")
+pub fn pre_commit(allowed_placeholders: &[String]) -> anyhow::Result<Result<(), ExitCode>> {
+    // `git diff` lists paths relative to the top level, and `git ls-files` takes pathspecs relative
+    // to where it runs, so both run there rather than in whatever subdirectory empd was run in
+    let top_level = git_output(".", &["rev-parse", "--show-toplevel"])?;
+
+    let top_level = String::from_utf8(top_level).context("git listed a path that is not UTF-8")?;
+
+    let directory = top_level.trim_end_matches('\n');
+
+    let mut placeholders = allowed_placeholders.to_vec();
+
+    placeholders.extend(git_config_values(directory, "empd.placeholder")?);
+
+    let staged_paths = git_output(
+        directory,
+        &[
+            "diff",
+            "--cached",
+            "--diff-filter=ACMR",
+            "--name-only",
+            "-z",
+        ],
+    )?;
+
+    let staged_paths = staged_paths
+        .split(|&ue| ue == b'\0')
+        .filter(|sl| !sl.is_empty())
+        .map(|sl| std::str::from_utf8(sl).context("git listed a path that is not UTF-8"))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if staged_paths.is_empty() {
+        return Ok(Ok(()));
+    }
+
+    let mut arguments = vec!["--literal-pathspecs", "ls-files", "--stage", "-z", "--"];
+
+    arguments.extend(staged_paths);
+
+    let index_entries = parse_index_entries(&git_output(directory, &arguments)?)?;
+
+    let mut rejected = 0_usize;
+
+    for index_entry in index_entries.iter().filter(|ie| ie.is_empty_file()) {
+        let is_placeholder = Path::new(&index_entry.path)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .is_some_and(|st| placeholders.iter().any(|pl| pl == st));
+
+        if is_placeholder {
+            continue;
+        }
+
+        println!(
+            " {}  Staged file \"{}\" is {}",
+            X.as_str().bold().red(),
+            index_entry.path.bold(),
+            "empty".bold().red()
+        );
+
+        rejected += 1_usize;
+    }
+
+    if rejected > 0_usize {
+        note!(
+            "Rejecting commit with {} empty files (allow placeholders with --allow-placeholder or `git config --add empd.placeholder <NAME>`)",
+            rejected.bold()
+        );
+
+        return Ok(Err(ExitCode::Failure));
+    }
+
+    Ok(Ok(()))
+}
+
+/// Runs `git` in `directory`, failing if it exits unsuccessfully
+fn git_output(directory: &str, arguments: &[&str]) -> anyhow::Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(arguments)
+        .output()
+        .context("Could not run git")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} exited unsuccessfully ({}): {}",
+            arguments.join(" "),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(output.stdout)
+}
+
+/// Values of a multi-valued Git configuration key, which are empty if it is not set
+fn git_config_values(directory: &str, key: &str) -> anyhow::Result<Vec<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(directory)
+        .args(["config", "--get-all", key])
+        .output()
+        .context("Could not run git")?;
+
+    // `git config` exits with 1 if the key is not set
+    if output.status.code() == Some(1_i32) {
+        return Ok(Vec::new());
+    }
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git config --get-all {key} exited unsuccessfully ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_owned)
+        .collect())
+}
+
+/// Parses the NUL-terminated records of `git ls-files --stage -z`
+fn parse_index_entries(output: &[u8]) -> anyhow::Result<Vec<IndexEntry>> {
+    let mut index_entries = Vec::<IndexEntry>::new();
+
+    for sl in output.split(|&ue| ue == b'\0') {
+        if sl.is_empty() {
+            continue;
+        }
+
+        let record = std::str::from_utf8(sl).context("git listed a path that is not UTF-8")?;
+
+        // "<mode> <object> <stage>\t<path>"
+        let (metadata, path) = record
+            .split_once('\t')
+            .with_context(|| format!("Could not parse git index entry \"{record}\""))?;
+
+        let mut split = metadata.split(' ');
+
+        let (Some(mode), Some(object)) = (split.next(), split.next()) else {
+            anyhow::bail!("Could not parse git index entry \"{record}\"");
+        };
+
+        index_entries.push(IndexEntry {
+            mode: mode.to_owned(),
+            object: object.to_owned(),
+            path: path.to_owned(),
+        });
+    }
+
+    Ok(index_entries)
+}
+
+fn last_commit(directory: &str, path: &str) -> anyhow::Result<String> {
+    let output = git_output(
+        directory,
+        &[
+            "--literal-pathspecs",
+            "log",
+            "-1",
+            "--format=%h %as %an: %s",
+            "--",
+            path,
+        ],
+    )?;
+
+    let string = String::from_utf8_lossy(&output).trim().to_owned();
+
+    if string.is_empty() {
+        Ok("not committed yet".to_owned())
+    } else {
+        Ok(format!("last commit: {string}"))
+    }
+}
@@ -0,0 +1,70 @@
+//! Hashes of non-empty files, so that deduplication or verification does not need a second pass
+
+use crate::signal;
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::{fmt::Write, fs::File, path::Path};
+
+#[derive(Clone, Copy, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgorithm {
+    Blake3,
+    Sha256,
+}
+
+impl HashAlgorithm {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Blake3 => "BLAKE3",
+            Self::Sha256 => "SHA-256",
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct FileHash {
+    pub algorithm: HashAlgorithm,
+    /// Lowercase hexadecimal, as printed by `b3sum` and `sha256sum`
+    pub digest: String,
+}
+
+pub fn hash_file(path_path: &Path, algorithm: HashAlgorithm) -> anyhow::Result<FileHash> {
+    let file = File::open(path_path).context("Could not open file to hash it")?;
+
+    let bytes = match algorithm {
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+
+            signal::read_chunks(&file, |sl| {
+                hasher.update(sl);
+
+                true
+            })
+            .context("Could not read file to hash it")?;
+
+            hasher.finalize().as_bytes().to_vec()
+        }
+        HashAlgorithm::Sha256 => {
+            let mut hasher = Sha256::new();
+
+            signal::read_chunks(&file, |sl| {
+                hasher.update(sl);
+
+                true
+            })
+            .context("Could not read file to hash it")?;
+
+            hasher.finalize().to_vec()
+        }
+    };
+
+    let mut digest = String::with_capacity(bytes.len() * 2_usize);
+
+    for ue in bytes {
+        let _ = write!(digest, "{ue:02x}");
+    }
+
+    Ok(FileHash { algorithm, digest })
+}
@@ -0,0 +1,328 @@
+//! Per-layer scan of container images (OCI image layouts and `docker save` archives) for empty
+//! directories, zero-byte files, and whiteouts
+//!
+//! Each layer is scanned on its own, so a directory that is empty in one layer may have content from
+//! another layer in the final image.
+
+use crate::{archive::GZIP_MAGIC, exit_code::ExitCode, signal};
+use anyhow::Context;
+use flate2::read::GzDecoder;
+use owo_colors::OwoColorize;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs::{self, File},
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+use tar::EntryType;
+
+const ZSTD_MAGIC: [u8; 4_usize] = [0x28_u8, 0xb5_u8, 0x2f_u8, 0xfd_u8];
+
+/// Prefix of the files that mark a path from a lower layer as deleted
+const WHITEOUT_PREFIX: &str = ".wh.";
+
+/// `docker save` writes one of these for each image in `manifest.json`
+#[derive(Deserialize)]
+struct DockerManifest {
+    #[serde(rename = "Layers")]
+    layers: Vec<String>,
+}
+
+#[derive(Default)]
+struct LayerFindings {
+    empty_directories: Vec<String>,
+    empty_files: Vec<String>,
+    whiteouts: Vec<String>,
+}
+
+pub fn scan(path_str: &str) -> anyhow::Result<Result<(), ExitCode>> {
+    let path_path = Path::new(path_str);
+
+    let layers = if path_path.is_dir() {
+        scan_oci_layout(path_path)?
+    } else {
+        scan_docker_save(path_path)?
+    };
+
+    let layers_len = layers.len();
+
+    for (us, (name, layer_findings)) in layers.iter().enumerate() {
+        let LayerFindings {
+            empty_directories,
+            empty_files,
+            whiteouts,
+        } = layer_findings;
+
+        println!(
+            "Layer {} of {} (\"{}\"): empty directories: {}, empty files: {}, whiteouts: {}",
+            us + 1_usize,
+            layers_len,
+            name.bold(),
+            empty_directories.len().bold(),
+            empty_files.len().bold(),
+            whiteouts.len().bold()
+        );
+
+        for (description, paths) in [
+            ("empty directory", empty_directories),
+            ("empty file", empty_files),
+            ("whiteout", whiteouts),
+        ] {
+            for st in paths {
+                println!("  {description} \"{}\"", st.bold());
+            }
+        }
+    }
+
+    Ok(Ok(()))
+}
+
+/// Follows `index.json` (and any nested indexes) to the layers of every manifest in the layout
+fn scan_oci_layout(path_path: &Path) -> anyhow::Result<Vec<(String, LayerFindings)>> {
+    let index = read_json(&path_path.join("index.json"))?;
+
+    let mut pending = vec![index];
+
+    let mut seen_digests = HashSet::<String>::new();
+
+    let mut layers = Vec::<(String, LayerFindings)>::new();
+
+    while let Some(va) = pending.pop() {
+        for (key, is_layer) in [("manifests", false), ("layers", true)] {
+            let Some(descriptors) = va.get(key).and_then(Value::as_array) else {
+                continue;
+            };
+
+            for descriptor in descriptors {
+                let digest = descriptor
+                    .get("digest")
+                    .and_then(Value::as_str)
+                    .context("Descriptor in OCI image layout has no digest")?;
+
+                if !seen_digests.insert(digest.to_owned()) {
+                    continue;
+                }
+
+                let blob_path = blob_path(path_path, digest)?;
+
+                if is_layer {
+                    let file = File::open(&blob_path).context("Could not open layer")?;
+
+                    layers.push((digest.to_owned(), scan_layer(file)?));
+                } else {
+                    pending.push(read_json(&blob_path)?);
+                }
+            }
+        }
+    }
+
+    Ok(layers)
+}
+
+fn scan_docker_save(path_path: &Path) -> anyhow::Result<Vec<(String, LayerFindings)>> {
+    // "manifest.json" may come after the layers, so the archive is read once to find it and once to
+    // scan the layers
+    let mut docker_manifests = None;
+
+    for re in open_tar(path_path)?.entries()? {
+        let mut entry = re.context("Could not read image archive entry")?;
+
+        if normalize(&entry.path()?.to_string_lossy()) == "manifest.json" {
+            docker_manifests = Some(
+                serde_json::from_reader::<_, Vec<DockerManifest>>(&mut entry)
+                    .context("Could not parse manifest.json")?,
+            );
+
+            break;
+        }
+    }
+
+    let docker_manifests = docker_manifests
+        .context("Image archive has no manifest.json (is it from `docker save`?)")?;
+
+    let layer_paths = docker_manifests
+        .iter()
+        .flat_map(|dm| dm.layers.iter().map(|st| normalize(st)))
+        .collect::<HashSet<_>>();
+
+    let mut layers = Vec::<(String, LayerFindings)>::new();
+
+    for re in open_tar(path_path)?.entries()? {
+        if signal::received().is_some() {
+            break;
+        }
+
+        let entry = re.context("Could not read image archive entry")?;
+
+        let name = normalize(&entry.path()?.to_string_lossy());
+
+        if layer_paths.contains(&name) {
+            let layer_findings = scan_layer(entry)?;
+
+            layers.push((name, layer_findings));
+        }
+    }
+
+    Ok(layers)
+}
+
+fn scan_layer(reader: impl Read) -> anyhow::Result<LayerFindings> {
+    let mut buf_reader = BufReader::new(reader);
+
+    let header = buf_reader.fill_buf().context("Could not read layer")?;
+
+    if header.starts_with(&ZSTD_MAGIC) {
+        anyhow::bail!("zstd-compressed layers are not supported");
+    }
+
+    if header.starts_with(&GZIP_MAGIC) {
+        scan_layer_tar(GzDecoder::new(buf_reader))
+    } else {
+        scan_layer_tar(buf_reader)
+    }
+}
+
+fn scan_layer_tar(reader: impl Read) -> anyhow::Result<LayerFindings> {
+    let mut layer_findings = LayerFindings::default();
+
+    let mut directories = BTreeSet::<String>::new();
+
+    // Directories that something in this layer is inside of
+    let mut parents = HashSet::<String>::new();
+
+    for re in tar::Archive::new(reader)
+        .entries()
+        .context("Could not read layer")?
+    {
+        if signal::received().is_some() {
+            break;
+        }
+
+        let entry = re.context("Could not read layer entry")?;
+
+        let path = normalize(&entry.path()?.to_string_lossy());
+
+        // The root of the layer
+        if path.is_empty() {
+            continue;
+        }
+
+        let mut ancestor = Path::new(&path).parent();
+
+        while let Some(pa) = ancestor {
+            parents.insert(pa.to_string_lossy().into_owned());
+
+            ancestor = pa.parent();
+        }
+
+        let is_whiteout = Path::new(&path)
+            .file_name()
+            .is_some_and(|os| os.to_string_lossy().starts_with(WHITEOUT_PREFIX));
+
+        match entry.header().entry_type() {
+            _ if is_whiteout => {
+                layer_findings.whiteouts.push(path);
+            }
+            EntryType::Directory => {
+                directories.insert(path);
+            }
+            EntryType::Regular | EntryType::Continuous if entry.size() == 0_u64 => {
+                layer_findings.empty_files.push(path);
+            }
+            _ => {}
+        }
+    }
+
+    layer_findings.empty_directories = directories
+        .into_iter()
+        .filter(|st| !parents.contains(st))
+        .collect();
+
+    Ok(layer_findings)
+}
+
+fn open_tar(path_path: &Path) -> anyhow::Result<tar::Archive<BufReader<File>>> {
+    let file = File::open(path_path).context("Could not open image archive")?;
+
+    Ok(tar::Archive::new(BufReader::new(file)))
+}
+
+/// Only accepts the digest algorithms that the OCI image specification registers, so that a digest
+/// from the image cannot name a path outside of "blobs" (e.g. "sha256:../../etc/passwd")
+fn blob_path(path_path: &Path, digest: &str) -> anyhow::Result<PathBuf> {
+    let (algorithm, encoded) = digest
+        .split_once(':')
+        .with_context(|| format!("Digest \"{digest}\" is malformed"))?;
+
+    let length = match algorithm {
+        "sha256" => 64_usize,
+        "sha512" => 128_usize,
+        _ => anyhow::bail!(
+            "Digest \"{digest}\" uses an unsupported algorithm (expected sha256 or sha512)"
+        ),
+    };
+
+    let is_lowercase_hex = encoded
+        .bytes()
+        .all(|ue| ue.is_ascii_digit() || (b'a'..=b'f').contains(&ue));
+
+    if encoded.len() != length || !is_lowercase_hex {
+        anyhow::bail!("Digest \"{digest}\" is malformed (expected {length} lowercase hexadecimal digits after \"{algorithm}:\")");
+    }
+
+    Ok(path_path.join("blobs").join(algorithm).join(encoded))
+}
+
+fn read_json(path_path: &Path) -> anyhow::Result<Value> {
+    let string = fs::read_to_string(path_path)
+        .with_context(|| format!("Could not read \"{}\"", path_path.display()))?;
+
+    serde_json::from_str::<Value>(&string)
+        .with_context(|| format!("Could not parse \"{}\"", path_path.display()))
+}
+
+/// Strips "./" and trailing slashes, which tar writers add inconsistently
+fn normalize(path_str: &str) -> String {
+    path_str
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blob_path_accepts_registered_digests() {
+        let sha256 = format!("sha256:{}", "0a".repeat(32_usize));
+
+        assert_eq!(
+            blob_path(Path::new("image"), &sha256).unwrap(),
+            Path::new("image/blobs/sha256").join("0a".repeat(32_usize))
+        );
+
+        assert!(blob_path(
+            Path::new("image"),
+            &format!("sha512:{}", "f".repeat(128_usize))
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn blob_path_rejects_other_digests() {
+        for digest in [
+            "sha256",
+            "sha256:../../etc/passwd",
+            &format!("md5:{}", "0".repeat(32_usize)),
+            &format!("sha256:{}", "0".repeat(63_usize)),
+            &format!("sha256:{}", "A".repeat(64_usize)),
+            &format!("sha512:{}", "0".repeat(64_usize)),
+            &format!("sha256:{}/..", "0".repeat(61_usize)),
+        ] {
+            assert!(blob_path(Path::new("image"), digest).is_err(), "{digest}");
+        }
+    }
+}
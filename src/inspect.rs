@@ -0,0 +1,1085 @@
+use crate::{
+    audit, exec,
+    exit_code::ExitCode,
+    file_system::{self, MountPoint, Subvolume},
+    hash::{self, FileHash, HashAlgorithm},
+    semantic::SemanticFormat,
+    signal,
+};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File, Metadata},
+    io::{self, ErrorKind, Read},
+    os::{
+        fd::{BorrowedFd, RawFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Component, Path, PathBuf},
+};
+
+/// What a path turned out to be, determined without printing anything or modifying the file system
+#[derive(Serialize)]
+pub struct Inspection {
+    pub path: String,
+    /// `None` if the path, or the file a symbolic link resolves to, does not exist
+    pub canonical_path: Option<String>,
+    #[serde(skip)]
+    pub metadata: Option<Metadata>,
+    #[serde(flatten)]
+    pub state: PathState,
+    /// Set for zero-length files on file systems like procfs, whose size does not reflect their content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pseudo_file_system: Option<&'static str>,
+    /// Hidden entries that were not counted, with `ignore_hidden`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ignored_hidden_entries: Vec<String>,
+    /// Set if the path, or the file a symbolic link resolves to, could not be reached
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub broken_component: Option<BrokenComponent>,
+    /// Set for directories that a file system is mounted on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mount_point: Option<MountPoint>,
+    /// Set for directories that are btrfs subvolumes or ZFS datasets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subvolume: Option<Subvolume>,
+    /// The size of a file that was counted as empty because it contains only NUL bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nul_bytes: Option<u64>,
+    /// The size of a file that was counted as empty because the `empty_when` command succeeded for it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub empty_when_bytes: Option<u64>,
+    /// The format of a file that was counted as empty because it holds no data in that format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub semantically_empty: Option<SemanticFormat>,
+    /// The hash of a non-empty file, with `hash`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<FileHash>,
+    /// When an empty path was last modified, with `show_mtime`, as RFC 3339 in UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+}
+
+/// The first component of a path that could not be resolved, and why
+#[derive(Serialize)]
+pub struct BrokenComponent {
+    /// The path up to and including the component
+    pub path: String,
+    /// Set if the component is a symbolic link, as the problem is then with what it points to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub reason: &'static str,
+}
+
+impl fmt::Display for BrokenComponent {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match &self.target {
+            // The link exists, so the reason is about its target
+            Some(st) => write!(
+                formatter,
+                "\"{st}\", the target of symbolic link \"{}\", {}",
+                self.path, self.reason
+            ),
+            None => write!(formatter, "\"{}\" {}", self.path, self.reason),
+        }
+    }
+}
+
+#[allow(clippy::struct_excessive_bools)]
+pub struct InspectOptions {
+    /// Read zero-length files to check if they are empty, as the size reported by procfs, sysfs, and
+    /// some FUSE and network file systems does not reflect their content
+    pub verify_read: bool,
+    /// The most that is read from each zero-length file when `verify_read` is set
+    pub max_read_bytes: u64,
+    /// Count the files in non-empty directories by extension
+    pub by_extension: bool,
+    /// Leave entries whose names start with "." out of directory counts, so that a directory with
+    /// only hidden entries is empty
+    pub ignore_hidden: bool,
+    /// Fail on directory entries that are not directories, files, or symbolic links, rather than
+    /// counting them as `other`
+    pub strict: bool,
+    /// Also count all descendants of non-empty directories
+    pub recursive_counts: bool,
+    /// Also count the descendants of non-empty directories at each depth
+    pub depth_profile: bool,
+    /// Count files that contain only NUL bytes, e.g. from preallocation or corruption, as empty
+    pub nul_only_is_empty: bool,
+    /// A command that counts a non-empty file as empty by succeeding for it, with "{}" as its path
+    pub empty_when: Option<String>,
+    /// Count files of this format that hold no data, e.g. "{}", as empty
+    pub semantic: Option<SemanticFormat>,
+    /// Hash non-empty files with this algorithm
+    pub hash: Option<HashAlgorithm>,
+    /// Record when empty paths were last modified
+    pub show_mtime: bool,
+}
+
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            verify_read: false,
+            max_read_bytes: 4_096_u64,
+            by_extension: false,
+            ignore_hidden: false,
+            strict: false,
+            recursive_counts: false,
+            depth_profile: false,
+            nul_only_is_empty: false,
+            empty_when: None,
+            semantic: None,
+            hash: None,
+            show_mtime: false,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case", tag = "state")]
+pub enum PathState {
+    NotFound,
+    PermissionDenied,
+    EmptyDirectory,
+    NonEmptyDirectory(DirectoryCounts),
+    EmptyFile,
+    NonEmptyFile {
+        bytes: u64,
+    },
+    DanglingSymlink {
+        target: String,
+        /// `target` resolved against the link's directory, if it is relative
+        #[serde(skip_serializing_if = "Option::is_none")]
+        absolute_target: Option<String>,
+    },
+    Symlink {
+        target: String,
+        /// `target` resolved against the link's directory, if it is relative
+        #[serde(skip_serializing_if = "Option::is_none")]
+        absolute_target: Option<String>,
+        /// What the link points to directly, which is a symlink if it is one link in a chain
+        target_kind: ItemKind,
+        /// Whether the file or directory the link ultimately resolves to is empty
+        target_empty: bool,
+        /// The size of the file the link ultimately resolves to, if it is a file
+        #[serde(skip_serializing_if = "Option::is_none")]
+        target_bytes: Option<u64>,
+    },
+    /// A symbolic link that resolves back to itself, either as the path or as one of its components
+    SymlinkLoop {
+        /// The links that were followed, from the looping link until one repeats
+        chain: Vec<String>,
+    },
+    /// A pipe, socket, or character device, which is read to the end rather than stat'd
+    EmptyStream,
+    NonEmptyStream {
+        bytes: u64,
+    },
+}
+
+#[derive(Clone, Copy, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Directory,
+    File,
+    Symlink,
+}
+
+#[derive(Default, Serialize)]
+pub struct DirectoryCounts {
+    pub directories: u32,
+    pub files: u32,
+    pub symlinks: u32,
+    /// Sockets, pipes, and devices
+    pub other: u32,
+    pub total_items: u32,
+    /// Entries whose names start with "."
+    pub hidden: u32,
+    /// Entries whose type could not be determined (e.g. because permission was denied), which are
+    /// counted in `total_items` but not in `directories`, `files`, or `symlinks`
+    pub unreadable: u32,
+    /// Entries that were deleted between being listed and having their type read, e.g. in a busy
+    /// spool directory, which are not counted in `total_items`
+    pub vanished: u32,
+    /// Files by extension ("" for files without one), if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub extensions: Option<BTreeMap<String, u32>>,
+    /// Totals over all descendants, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recursive: Option<RecursiveCounts>,
+    /// The number of descendants at each depth, starting with the entries themselves, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub depth_profile: Option<Vec<u64>>,
+    /// Problems reading individual entries, which are reported as warnings rather than failing the
+    /// whole check
+    #[serde(skip)]
+    pub entry_errors: Vec<String>,
+}
+
+/// Totals over all descendants of a directory, found without following symbolic links
+#[derive(Default, Serialize)]
+pub struct RecursiveCounts {
+    pub directories: u64,
+    pub files: u64,
+    pub symlinks: u64,
+    pub other: u64,
+    /// The total size of the files
+    pub bytes: u64,
+    /// Descendants that were deleted between being listed and being read, which are not counted
+    pub vanished: u64,
+}
+
+impl ItemKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Directory => "directory",
+            Self::File => "file",
+            Self::Symlink => "symlink",
+        }
+    }
+}
+
+impl Inspection {
+    /// The canonical path, or else the path made absolute (e.g. for a dangling symbolic link), which
+    /// is how `--state` files and SARIF logs identify it, so that `empd diff` can compare them. `None`
+    /// for standard input.
+    pub fn absolute_path(&self) -> Option<String> {
+        if self.path == "-" {
+            return None;
+        }
+
+        match &self.canonical_path {
+            Some(st) => Some(st.clone()),
+            None => std::path::absolute(&self.path)
+                .ok()
+                .map(|pa| pa.to_string_lossy().into_owned()),
+        }
+    }
+
+    /// `None` if the path could not be accessed
+    pub fn kind(&self) -> Option<ItemKind> {
+        match self.state {
+            PathState::NotFound
+            | PathState::PermissionDenied
+            | PathState::EmptyStream
+            | PathState::NonEmptyStream { .. } => None,
+            PathState::EmptyDirectory | PathState::NonEmptyDirectory(_) => {
+                Some(ItemKind::Directory)
+            }
+            PathState::EmptyFile | PathState::NonEmptyFile { .. } => Some(ItemKind::File),
+            PathState::DanglingSymlink { .. }
+            | PathState::Symlink { .. }
+            | PathState::SymlinkLoop { .. } => Some(ItemKind::Symlink),
+        }
+    }
+
+    /// The "state" tag this serializes with, e.g. `empty_file`
+    pub fn state_name(&self) -> anyhow::Result<String> {
+        let value = serde_json::to_value(&self.state)?;
+
+        value
+            .get("state")
+            .and_then(serde_json::Value::as_str)
+            .map(str::to_owned)
+            .context("Path state has no name")
+    }
+
+    /// The exit code for the path, before anything is deleted
+    pub fn result(&self) -> Result<(), ExitCode> {
+        match self.state {
+            PathState::NotFound => Err(ExitCode::NotFound),
+            PathState::PermissionDenied => Err(ExitCode::PermissionDenied),
+            PathState::NonEmptyFile { .. } | PathState::NonEmptyStream { .. } => {
+                Err(ExitCode::NonEmptyFile)
+            }
+            PathState::NonEmptyDirectory(_) => Err(ExitCode::NonEmptyDirectory),
+            PathState::Symlink { .. } => Err(ExitCode::Symlink),
+            PathState::SymlinkLoop { .. } => Err(ExitCode::SymlinkLoop),
+            PathState::EmptyDirectory
+            | PathState::EmptyFile
+            | PathState::DanglingSymlink { .. }
+            | PathState::EmptyStream => Ok(()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!(
+            self.state,
+            PathState::EmptyDirectory
+                | PathState::EmptyFile
+                | PathState::DanglingSymlink { .. }
+                | PathState::EmptyStream
+        )
+    }
+}
+
+pub fn inspect(path_str: &str, inspect_options: &InspectOptions) -> anyhow::Result<Inspection> {
+    let path_path = Path::new(path_str);
+
+    let mut pseudo_file_system = None;
+
+    let mut ignored_hidden_entries = Vec::<String>::new();
+
+    let mut nul_bytes = None;
+
+    let mut empty_when_bytes = None;
+
+    let mut semantically_empty = None;
+
+    let (canonical_path, metadata, state) = match fs::symlink_metadata(path_path) {
+        Err(er) => (None, None, inaccessible_state(path_path, er)?),
+        Ok(me) => match me {
+            me if me.is_dir() => {
+                let canonical_path =
+                    canonicalize(path_path)?.context("Could not canonicalize directory path")?;
+
+                let directory_counts;
+
+                (directory_counts, ignored_hidden_entries) =
+                    count_directory_entries(path_path, inspect_options)?;
+
+                let state = if directory_counts.total_items > 0_u32 {
+                    PathState::NonEmptyDirectory(directory_counts)
+                } else {
+                    PathState::EmptyDirectory
+                };
+
+                (Some(canonical_path), Some(me), state)
+            }
+            me if me.is_file() => {
+                let canonical_path =
+                    canonicalize(path_path)?.context("Could not canonicalize file path")?;
+
+                let file_state = file_state(path_path, me.len(), inspect_options)?;
+
+                pseudo_file_system = file_state.pseudo_file_system;
+                nul_bytes = file_state.nul_bytes;
+                empty_when_bytes = file_state.empty_when_bytes;
+                semantically_empty = file_state.semantically_empty;
+
+                (Some(canonical_path), Some(me), file_state.state)
+            }
+            me if me.is_symlink()
+                && fs::canonicalize(path_path)
+                    .is_err_and(|er| er.raw_os_error() == Some(libc::ELOOP)) =>
+            {
+                let chain = symlink_chain(path_path)?;
+
+                (None, Some(me), PathState::SymlinkLoop { chain })
+            }
+            me if me.is_symlink() => {
+                let (canonical_path, state) = inspect_symlink(path_path)?;
+
+                (canonical_path, Some(me), state)
+            }
+            _ => {
+                anyhow::bail!("Path \"{path_str}\" is not a directory, file, or symlink")
+            }
+        },
+    };
+
+    let broken_component = canonical_path
+        .is_none()
+        .then(|| find_broken_component(path_path))
+        .flatten();
+
+    let (mount_point, subvolume) = directory_mounts(&state, canonical_path.as_deref(), path_path);
+
+    // Regular files only, as reading a stream would consume it
+    let hash = match (&state, inspect_options.hash) {
+        (PathState::NonEmptyFile { .. }, Some(ha)) => Some(hash::hash_file(path_path, ha)?),
+        _ => None,
+    };
+
+    let mut inspection = Inspection {
+        path: path_str.to_owned(),
+        canonical_path,
+        metadata,
+        state,
+        pseudo_file_system,
+        ignored_hidden_entries,
+        broken_component,
+        mount_point,
+        subvolume,
+        nul_bytes,
+        empty_when_bytes,
+        semantically_empty,
+        hash,
+        modified: None,
+    };
+
+    // Stale empty paths are usually the ones worth deleting, and fresh ones may be in use
+    if inspect_options.show_mtime && inspection.is_empty() {
+        inspection.modified = inspection
+            .metadata
+            .as_ref()
+            .map(|me| {
+                me.modified()
+                    .map_err(anyhow::Error::from)
+                    .and_then(audit::format_timestamp)
+            })
+            .transpose()?;
+    }
+
+    Ok(inspection)
+}
+
+/// What `file_state` found out about a regular file, for the `Inspection` fields of the same names
+struct FileState {
+    state: PathState,
+    pseudo_file_system: Option<&'static str>,
+    nul_bytes: Option<u64>,
+    empty_when_bytes: Option<u64>,
+    semantically_empty: Option<SemanticFormat>,
+}
+
+/// Inspects a regular file that is `len` bytes long
+fn file_state(
+    path_path: &Path,
+    len: u64,
+    inspect_options: &InspectOptions,
+) -> anyhow::Result<FileState> {
+    let mut file_state = FileState {
+        state: PathState::NonEmptyFile { bytes: len },
+        pseudo_file_system: None,
+        nul_bytes: None,
+        empty_when_bytes: None,
+        semantically_empty: None,
+    };
+
+    if len > 0_u64 {
+        if inspect_options.nul_only_is_empty && contains_only_nul_bytes(path_path)? {
+            file_state.state = PathState::EmptyFile;
+            file_state.nul_bytes = Some(len);
+        } else if let Some(se) = inspect_options.semantic {
+            if se.is_empty(path_path, len)? {
+                file_state.state = PathState::EmptyFile;
+                file_state.semantically_empty = Some(se);
+            }
+        }
+
+        if let (PathState::NonEmptyFile { .. }, Some(st)) =
+            (&file_state.state, &inspect_options.empty_when)
+        {
+            let path_str = path_path
+                .to_str()
+                .context("Could not convert path to a UTF-8 string")?;
+
+            if exec::predicate_holds(st, path_str)? {
+                file_state.state = PathState::EmptyFile;
+                file_state.empty_when_bytes = Some(len);
+            }
+        }
+
+        return Ok(file_state);
+    }
+
+    file_state.pseudo_file_system = file_system::magic(path_path)
+        .ok()
+        .and_then(file_system::pseudo_file_system_name);
+
+    let bytes = if inspect_options.verify_read {
+        read_bounded(path_path, inspect_options.max_read_bytes)?
+    } else {
+        0_u64
+    };
+
+    file_state.state = if bytes > 0_u64 {
+        PathState::NonEmptyFile { bytes }
+    } else {
+        PathState::EmptyFile
+    };
+
+    Ok(file_state)
+}
+
+/// Reads the file until the first byte that is not NUL
+fn contains_only_nul_bytes(path_path: &Path) -> anyhow::Result<bool> {
+    let file = File::open(path_path).context("Could not open file to read it")?;
+
+    let mut only_nul_bytes = true;
+
+    signal::read_chunks(file, |sl| {
+        only_nul_bytes = sl.iter().all(|ue| *ue == 0_u8);
+
+        only_nul_bytes
+    })
+    .context("Could not read file")?;
+
+    Ok(only_nul_bytes)
+}
+
+/// The canonical path of a symbolic link that is not part of a loop (`None` if it dangles), and its state
+fn inspect_symlink(path_path: &Path) -> anyhow::Result<(Option<String>, PathState)> {
+    let link_path_buf = path_path
+        .read_link()
+        .context("Could not read symbolic link")?;
+
+    let target = link_path_buf
+        .to_str()
+        .context("Could not convert symbolic link path to a UTF-8 string")?
+        .to_owned();
+
+    let canonical_path = canonicalize(path_path)?;
+
+    // Relative targets are relative to the directory containing the link, not the current directory
+    let target_path_buf = path_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(&link_path_buf);
+
+    let absolute_target = if link_path_buf.is_relative() {
+        Some(absolute_lexically(&target_path_buf)?)
+    } else {
+        None
+    };
+
+    let state = if canonical_path.is_some() {
+        inspect_symlink_target(path_path, &target_path_buf, target, absolute_target)?
+    } else {
+        PathState::DanglingSymlink {
+            target,
+            absolute_target,
+        }
+    };
+
+    Ok((canonical_path, state))
+}
+
+/// What is mounted on a directory, and whether it is a subvolume or dataset
+fn directory_mounts(
+    state: &PathState,
+    canonical_path: Option<&str>,
+    path_path: &Path,
+) -> (Option<MountPoint>, Option<Subvolume>) {
+    match (state, canonical_path) {
+        (PathState::EmptyDirectory | PathState::NonEmptyDirectory(_), Some(st)) => {
+            let mount_point = file_system::mount_point(st);
+
+            let subvolume = file_system::subvolume(path_path, mount_point.as_ref());
+
+            (mount_point, subvolume)
+        }
+        _ => (None, None),
+    }
+}
+
+/// The state of a path that could not be stat'd
+fn inaccessible_state(path_path: &Path, error: io::Error) -> anyhow::Result<PathState> {
+    match error.kind() {
+        ErrorKind::NotFound => Ok(PathState::NotFound),
+        ErrorKind::PermissionDenied => Ok(PathState::PermissionDenied),
+        _ if error.raw_os_error() == Some(libc::ELOOP) => {
+            let looping_path_buf = find_broken_component(path_path)
+                .map_or_else(|| path_path.to_path_buf(), |br| PathBuf::from(br.path));
+
+            Ok(PathState::SymlinkLoop {
+                chain: symlink_chain(&looping_path_buf)?,
+            })
+        }
+        _ => Err(with_broken_component(error.into(), path_path)),
+    }
+}
+
+/// The symbolic links followed from `path_path` until one repeats, e.g. `["a", "/tmp/b", "/tmp/a"]`
+fn symlink_chain(path_path: &Path) -> anyhow::Result<Vec<String>> {
+    // The first link is shown as given, and the rest as absolute paths, which are compared
+    let mut chain = vec![path_path.to_string_lossy().into_owned()];
+
+    let mut seen = vec![absolute_lexically(path_path)?];
+
+    let mut link_path_buf = path_path.to_path_buf();
+
+    // Linux gives up after 40 links, even if none of them repeat
+    while let Ok(pa) = link_path_buf.read_link() {
+        link_path_buf = link_path_buf
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(pa);
+
+        let absolute_path = absolute_lexically(&link_path_buf)?;
+
+        let repeated = seen.contains(&absolute_path);
+
+        chain.push(absolute_path.clone());
+        seen.push(absolute_path);
+
+        if repeated || chain.len() > 40_usize {
+            break;
+        }
+    }
+
+    Ok(chain)
+}
+
+/// Makes `path_path` absolute and removes ".." components without resolving symbolic links, as the
+/// path may not exist
+fn absolute_lexically(path_path: &Path) -> anyhow::Result<String> {
+    let absolute_path_buf =
+        std::path::absolute(path_path).context("Could not make symbolic link target absolute")?;
+
+    let normalized_path_buf = absolute_path_buf
+        .components()
+        .fold(PathBuf::new(), |mut pa, co| {
+            if co == Component::ParentDir {
+                pa.pop();
+            } else {
+                pa.push(co);
+            }
+
+            pa
+        });
+
+    Ok(normalized_path_buf
+        .to_str()
+        .context("Could not convert symbolic link path to a UTF-8 string")?
+        .to_owned())
+}
+
+/// Describes what a symbolic link that does not dangle points to
+fn inspect_symlink_target(
+    path_path: &Path,
+    target_path: &Path,
+    target: String,
+    absolute_target: Option<String>,
+) -> anyhow::Result<PathState> {
+    let target_kind = if fs::symlink_metadata(target_path)
+        .context("Could not get symbolic link target metadata")?
+        .is_symlink()
+    {
+        ItemKind::Symlink
+    } else if target_path.is_dir() {
+        ItemKind::Directory
+    } else {
+        ItemKind::File
+    };
+
+    let resolved_metadata = fs::metadata(path_path)
+        .context("Could not get metadata of the path the symbolic link resolves to")?;
+
+    let (target_empty, target_bytes) = if resolved_metadata.is_dir() {
+        let is_empty = path_path
+            .read_dir()
+            .context("Could not read directory the symbolic link resolves to")?
+            .next()
+            .is_none();
+
+        (is_empty, None)
+    } else {
+        (
+            resolved_metadata.len() == 0_u64,
+            Some(resolved_metadata.len()),
+        )
+    };
+
+    Ok(PathState::Symlink {
+        target,
+        absolute_target,
+        target_kind,
+        target_empty,
+        target_bytes,
+    })
+}
+
+/// Returns how many bytes could be read, up to `max_read_bytes`
+fn read_bounded(path_path: &Path, max_read_bytes: u64) -> anyhow::Result<u64> {
+    let mut buffer = Vec::<u8>::new();
+
+    File::open(path_path)
+        .context("Could not open file to read it")?
+        .take(max_read_bytes)
+        .read_to_end(&mut buffer)
+        .context("Could not read file")?;
+
+    Ok(u64::try_from(buffer.len())?)
+}
+
+/// Inspects an already-open file descriptor with `fstat`, so that there is no path to race on,
+/// returning `None` if SIGINT or SIGTERM is received while reading a stream
+///
+/// `path_str` is only used to refer to the file descriptor in output.
+pub fn inspect_fd(fd: RawFd, path_str: &str) -> anyhow::Result<Option<Inspection>> {
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1_i32 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Could not access file descriptor {fd}"));
+    }
+
+    // SAFETY: `fd` was just checked to be open, and nothing in this process closes it, so it stays
+    // valid while borrowed. It is only borrowed long enough to be duplicated.
+    //
+    // Duplicated so that closing the `File` leaves the inherited file descriptor open
+    let owned_fd = unsafe { BorrowedFd::borrow_raw(fd) }
+        .try_clone_to_owned()
+        .with_context(|| format!("Could not access file descriptor {fd}"))?;
+
+    let file = File::from(owned_fd);
+
+    let metadata = file
+        .metadata()
+        .context("Could not get file descriptor metadata")?;
+
+    // The "magic" link in procfs refers to the open file itself, not whatever is at its path now
+    let proc_path = format!("/proc/self/fd/{fd}");
+
+    let canonical_path = fs::read_link(&proc_path)
+        .ok()
+        .and_then(|pa| pa.to_str().map(ToOwned::to_owned))
+        .filter(|st| st.starts_with('/'));
+
+    let state = match &metadata {
+        me if me.is_dir() => {
+            let (directory_counts, _) =
+                count_directory_entries(Path::new(&proc_path), &InspectOptions::default())?;
+
+            if directory_counts.total_items > 0_u32 {
+                PathState::NonEmptyDirectory(directory_counts)
+            } else {
+                PathState::EmptyDirectory
+            }
+        }
+        me if me.is_file() => {
+            let len = me.len();
+
+            if len > 0_u64 {
+                PathState::NonEmptyFile { bytes: len }
+            } else {
+                PathState::EmptyFile
+            }
+        }
+        _ => {
+            let Some(bytes) = signal::count_bytes(&file)? else {
+                return Ok(None);
+            };
+
+            if bytes > 0_u64 {
+                PathState::NonEmptyStream { bytes }
+            } else {
+                PathState::EmptyStream
+            }
+        }
+    };
+
+    Ok(Some(Inspection {
+        path: path_str.to_owned(),
+        canonical_path,
+        metadata: Some(metadata),
+        state,
+        pseudo_file_system: None,
+        ignored_hidden_entries: Vec::new(),
+        broken_component: None,
+        mount_point: None,
+        subvolume: None,
+        nul_bytes: None,
+        empty_when_bytes: None,
+        semantically_empty: None,
+        hash: None,
+        modified: None,
+    }))
+}
+
+/// Also returns the names of the hidden entries left out with `ignore_hidden`
+fn count_directory_entries(
+    path_path: &Path,
+    inspect_options: &InspectOptions,
+) -> anyhow::Result<(DirectoryCounts, Vec<String>)> {
+    let read_dir = path_path.read_dir().context("Could not read directory")?;
+
+    let mut directory_counts = DirectoryCounts {
+        extensions: inspect_options
+            .by_extension
+            .then(BTreeMap::<String, u32>::new),
+        ..DirectoryCounts::default()
+    };
+
+    let mut ignored_hidden_entries = Vec::<String>::new();
+
+    for re in read_dir {
+        if signal::received().is_some() {
+            break;
+        }
+
+        let di = match re {
+            Ok(di) => di,
+            // Where the listing would continue is unknown, so stop
+            Err(er) => {
+                directory_counts
+                    .entry_errors
+                    .push(format!("Could not read the next directory entry: {er}"));
+
+                break;
+            }
+        };
+
+        let is_hidden = di.file_name().as_bytes().starts_with(b".");
+
+        if is_hidden && inspect_options.ignore_hidden {
+            ignored_hidden_entries.push(di.file_name().to_string_lossy().into_owned());
+
+            continue;
+        }
+
+        // Only falls back to `lstat`, which can be denied, if the file system does not report types
+        let fi = match di.file_type() {
+            Ok(fi) => Some(fi),
+            Err(er) if er.kind() == ErrorKind::NotFound => {
+                directory_counts.vanished += 1_u32;
+
+                continue;
+            }
+            Err(er) => {
+                if er.kind() != ErrorKind::PermissionDenied {
+                    directory_counts.entry_errors.push(format!(
+                        "Could not get the type of directory entry \"{}\": {er}",
+                        di.file_name().to_string_lossy()
+                    ));
+                }
+
+                None
+            }
+        };
+
+        if is_hidden {
+            directory_counts.hidden += 1_u32;
+        }
+
+        let Some(fi) = fi else {
+            directory_counts.unreadable += 1_u32;
+
+            continue;
+        };
+
+        match fi {
+            fi if fi.is_dir() => {
+                directory_counts.directories += 1_u32;
+            }
+            fi if fi.is_file() => {
+                directory_counts.files += 1_u32;
+
+                if let Some(bt) = &mut directory_counts.extensions {
+                    let extension = Path::new(&di.file_name())
+                        .extension()
+                        .map(|os| os.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+
+                    *bt.entry(extension).or_insert(0_u32) += 1_u32;
+                }
+            }
+            fi if fi.is_symlink() => {
+                directory_counts.symlinks += 1_u32;
+            }
+            _ if inspect_options.strict => {
+                anyhow::bail!(
+                    "Encountered directory entry that is not a directory, file, or symlink"
+                );
+            }
+            _ => {
+                directory_counts.other += 1_u32;
+            }
+        }
+    }
+
+    let total_items = directory_counts.directories
+        + directory_counts.files
+        + directory_counts.symlinks
+        + directory_counts.other
+        + directory_counts.unreadable;
+
+    directory_counts.total_items = total_items;
+
+    // Claiming that a directory is empty when its listing was cut short could get it deleted
+    if total_items == 0_u32 {
+        if let Some(st) = directory_counts.entry_errors.first() {
+            anyhow::bail!("{st}");
+        }
+    }
+
+    if (inspect_options.recursive_counts || inspect_options.depth_profile) && total_items > 0_u32 {
+        let (recursive_counts, depth_profile) =
+            count_descendants(path_path, &mut directory_counts.entry_errors);
+
+        directory_counts.recursive = inspect_options.recursive_counts.then_some(recursive_counts);
+        directory_counts.depth_profile = inspect_options.depth_profile.then_some(depth_profile);
+    }
+
+    ignored_hidden_entries.sort();
+
+    Ok((directory_counts, ignored_hidden_entries))
+}
+
+/// Walks the whole tree under `path_path`, adding directories that could not be read to
+/// `entry_errors` and carrying on. Also returns the number of entries at each depth.
+fn count_descendants(
+    path_path: &Path,
+    entry_errors: &mut Vec<String>,
+) -> (RecursiveCounts, Vec<u64>) {
+    let mut recursive_counts = RecursiveCounts::default();
+
+    let mut depth_profile = Vec::<u64>::new();
+
+    // Iterative, as trees can be deeper than the stack allows
+    let mut pending_directories = vec![(path_path.to_path_buf(), 0_usize)];
+
+    while let Some((pa, us)) = pending_directories.pop() {
+        // The counts are incomplete, which the interrupted exit code reports
+        if signal::received().is_some() {
+            break;
+        }
+
+        let read_dir = match pa.read_dir() {
+            Ok(re) => re,
+            // It existed when it was counted, and has no descendants left to count
+            Err(er) if er.kind() == ErrorKind::NotFound => continue,
+            Err(er) => {
+                entry_errors.push(format!(
+                    "Could not read directory \"{}\": {er}",
+                    pa.display()
+                ));
+
+                continue;
+            }
+        };
+
+        for re in read_dir {
+            let di = match re {
+                Ok(di) => di,
+                Err(er) => {
+                    entry_errors.push(format!(
+                        "Could not read the next entry of directory \"{}\": {er}",
+                        pa.display()
+                    ));
+
+                    break;
+                }
+            };
+
+            // Does not follow symbolic links
+            let me = di.metadata();
+
+            if me
+                .as_ref()
+                .is_err_and(|er| er.kind() == ErrorKind::NotFound)
+            {
+                recursive_counts.vanished += 1_u64;
+
+                continue;
+            }
+
+            if depth_profile.len() <= us {
+                depth_profile.resize(us + 1_usize, 0_u64);
+            }
+
+            depth_profile[us] += 1_u64;
+
+            let me = match me {
+                Ok(me) => me,
+                Err(er) => {
+                    entry_errors.push(format!(
+                        "Could not get the metadata of \"{}\": {er}",
+                        di.path().display()
+                    ));
+
+                    continue;
+                }
+            };
+
+            match me.file_type() {
+                fi if fi.is_dir() => {
+                    recursive_counts.directories += 1_u64;
+
+                    pending_directories.push((di.path(), us + 1_usize));
+                }
+                fi if fi.is_file() => {
+                    recursive_counts.files += 1_u64;
+                    recursive_counts.bytes += me.len();
+                }
+                fi if fi.is_symlink() => {
+                    recursive_counts.symlinks += 1_u64;
+                }
+                _ => {
+                    recursive_counts.other += 1_u64;
+                }
+            }
+        }
+    }
+
+    (recursive_counts, depth_profile)
+}
+
+fn canonicalize(path_path: &Path) -> anyhow::Result<Option<String>> {
+    let canonicalize_result = fs::canonicalize(path_path);
+
+    let option = match canonicalize_result {
+        Ok(pa) => {
+            let path_buf_str = pa
+                .to_str()
+                .context("Could not convert path to a UTF-8 string")?;
+
+            Some(path_buf_str.to_owned())
+        }
+        Err(er) => match er.kind() {
+            ErrorKind::NotFound => None,
+            _ => {
+                return Err(with_broken_component(er.into(), path_path));
+            }
+        },
+    };
+
+    Ok(option)
+}
+
+/// Resolves ever longer prefixes of `path_path` to find the first component that cannot be resolved,
+/// e.g. a missing directory in the middle of the path, or a symbolic link that is part of a loop
+fn find_broken_component(path_path: &Path) -> Option<BrokenComponent> {
+    let mut prefix_path_buf = PathBuf::new();
+
+    for co in path_path.components() {
+        prefix_path_buf.push(co);
+
+        let Err(er) = fs::canonicalize(&prefix_path_buf) else {
+            continue;
+        };
+
+        let reason = match er.raw_os_error() {
+            Some(libc::ENOENT) => "does not exist",
+            Some(libc::EACCES) => "could not be searched (permission denied)",
+            Some(libc::ELOOP) => "is part of a symbolic link loop",
+            Some(libc::ENOTDIR) => "is not a directory",
+            _ => "could not be resolved",
+        };
+
+        // These are problems with the directory the component is looked up in, not the component
+        if matches!(er.raw_os_error(), Some(libc::EACCES | libc::ENOTDIR)) {
+            if let Some(pa) = prefix_path_buf
+                .parent()
+                .filter(|pa| !pa.as_os_str().is_empty())
+            {
+                prefix_path_buf = pa.to_path_buf();
+            }
+        }
+
+        let target = prefix_path_buf
+            .read_link()
+            .ok()
+            .map(|pa| pa.to_string_lossy().into_owned());
+
+        return Some(BrokenComponent {
+            path: prefix_path_buf.to_string_lossy().into_owned(),
+            target,
+            reason,
+        });
+    }
+
+    None
+}
+
+fn with_broken_component(error: anyhow::Error, path_path: &Path) -> anyhow::Error {
+    match find_broken_component(path_path) {
+        Some(br) => error.context(format!(
+            "Could not resolve path \"{}\", because {br}",
+            path_path.display()
+        )),
+        None => error,
+    }
+}
@@ -0,0 +1,70 @@
+use crate::{output::note, signal};
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, ErrorKind},
+    os::{fd::AsRawFd, unix::fs::OpenOptionsExt},
+};
+
+/// An exclusive advisory lock (`flock`), released when dropped
+pub struct PathLock {
+    _file: File,
+}
+
+/// Locks the file or directory at `path_str` itself, waiting if a cooperating tool holds the lock
+///
+/// Returns `None` for paths that cannot be locked: symbolic links (which are never followed, so that
+/// the lock is not taken on something other than what is deleted) and paths that do not exist.
+pub fn lock(path_str: &str) -> anyhow::Result<Option<PathLock>> {
+    // Opening a FIFO would otherwise wait for a writer
+    let file = match OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW | libc::O_NONBLOCK)
+        .open(path_str)
+    {
+        Ok(fi) => fi,
+        Err(er) if er.raw_os_error() == Some(libc::ELOOP) => {
+            note!(
+                "Not locking \"{}\" because it is a symbolic link",
+                path_str.bold()
+            );
+
+            return Ok(None);
+        }
+        Err(er) if er.kind() == ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(er) => {
+            return Err(er).context("Could not open path to lock it");
+        }
+    };
+
+    let fd = file.as_raw_fd();
+
+    if unsafe { libc::flock(fd, libc::LOCK_EX | libc::LOCK_NB) } != 0_i32 {
+        let error = io::Error::last_os_error();
+
+        if error.kind() != ErrorKind::WouldBlock {
+            return Err(error).context("Could not lock path");
+        }
+
+        note!(
+            "Waiting for another process to release its lock on \"{}\"",
+            path_str.bold()
+        );
+
+        while unsafe { libc::flock(fd, libc::LOCK_EX) } != 0_i32 {
+            let error = io::Error::last_os_error();
+
+            // Signal handlers are installed without `SA_RESTART`, so SIGINT and SIGTERM end the wait
+            if error.kind() != ErrorKind::Interrupted {
+                return Err(error).context("Could not lock path");
+            }
+
+            signal::check()?;
+        }
+    }
+
+    Ok(Some(PathLock { _file: file }))
+}
@@ -0,0 +1,195 @@
+use anyhow::Context;
+use clap::ValueEnum;
+use std::{
+    ffi::CString,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+use tracing::{Level, Metadata};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{
+    fmt::{writer::BoxMakeWriter, MakeWriter},
+    layer::SubscriberExt,
+    util::SubscriberInitExt,
+    EnvFilter,
+};
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable, multi-line records
+    Pretty,
+    /// One JSON object per record, for log ingestion
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum LogTarget {
+    Stderr,
+    /// The systemd journal, with structured fields (ignores the log format)
+    Journald,
+    /// The local syslog daemon
+    Syslog,
+}
+
+/// Must be kept alive until the end of the run, so that exported spans are flushed
+pub struct TracingGuard {
+    #[cfg(feature = "otel")]
+    tracer_provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Drop for TracingGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(tr) = &self.tracer_provider {
+            if let Err(er) = tr.shutdown() {
+                eprintln!("Could not flush OpenTelemetry spans: {er}");
+            }
+        }
+    }
+}
+
+pub fn init_tracing(
+    log_format: LogFormat,
+    log_target: LogTarget,
+    log_file: Option<&Path>,
+) -> anyhow::Result<TracingGuard> {
+    let registry = tracing_subscriber::registry().with(EnvFilter::from_default_env());
+
+    #[cfg(feature = "otel")]
+    let (registry, tracing_guard) = {
+        let tracer_provider = otel_tracer_provider()?;
+
+        let layer = tracer_provider.as_ref().map(|tr| {
+            tracing_opentelemetry::layer()
+                .with_tracer(opentelemetry::trace::TracerProvider::tracer(tr, "empd"))
+        });
+
+        (registry.with(layer), TracingGuard { tracer_provider })
+    };
+
+    #[cfg(not(feature = "otel"))]
+    let tracing_guard = TracingGuard {};
+
+    let (make_writer, ansi) = match (log_target, log_file) {
+        (_, Some(pa)) => (BoxMakeWriter::new(rolling_file_appender(pa)?), false),
+        (LogTarget::Journald, None) => {
+            let layer = tracing_journald::layer()
+                .context("Could not connect to journald")?
+                .with_syslog_identifier("empd".to_owned());
+
+            registry.with(layer).init();
+
+            return Ok(tracing_guard);
+        }
+        (LogTarget::Syslog, None) => (BoxMakeWriter::new(SyslogMakeWriter::open()), false),
+        (LogTarget::Stderr, None) => (BoxMakeWriter::new(io::stderr), true),
+    };
+
+    let fmt_layer = tracing_subscriber::fmt::layer()
+        .with_ansi(ansi)
+        .with_writer(make_writer);
+
+    match log_format {
+        LogFormat::Pretty => registry.with(fmt_layer.pretty()).init(),
+        LogFormat::Json => registry.with(fmt_layer.json()).init(),
+    }
+
+    Ok(tracing_guard)
+}
+
+/// Follows the OpenTelemetry convention of only exporting when an endpoint is configured, so that
+/// builds with the feature enabled do not try to reach a collector that is not there
+#[cfg(feature = "otel")]
+fn otel_tracer_provider() -> anyhow::Result<Option<opentelemetry_sdk::trace::TracerProvider>> {
+    if std::env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_none() {
+        return Ok(None);
+    }
+
+    let tracer_provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().http())
+        .install_simple()
+        .context("Could not set up OpenTelemetry span export")?;
+
+    Ok(Some(tracer_provider))
+}
+
+// Log files keep their history across runs without cluttering stderr (e.g. cron mail), which is left
+// for user-facing messages
+fn rolling_file_appender(path: &Path) -> anyhow::Result<RollingFileAppender> {
+    let file_name = path
+        .file_name()
+        .context("Log file path has no file name")?
+        .to_str()
+        .context("Could not convert log file name to a UTF-8 string")?;
+
+    let directory = match path.parent() {
+        Some(pa) if !pa.as_os_str().is_empty() => pa.to_owned(),
+        _ => PathBuf::from("."),
+    };
+
+    RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(file_name)
+        .max_log_files(7_usize)
+        .build(directory)
+        .context("Could not open log file")
+}
+
+struct SyslogMakeWriter;
+
+impl SyslogMakeWriter {
+    fn open() -> Self {
+        // `openlog` keeps the pointer, so the identifier must live for the rest of the process
+        unsafe {
+            libc::openlog(c"empd".as_ptr(), libc::LOG_PID, libc::LOG_USER);
+        }
+
+        Self
+    }
+}
+
+struct SyslogWriter {
+    priority: libc::c_int,
+}
+
+impl<'a> MakeWriter<'a> for SyslogMakeWriter {
+    type Writer = SyslogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        SyslogWriter {
+            priority: libc::LOG_INFO,
+        }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        let priority = match *meta.level() {
+            Level::ERROR => libc::LOG_ERR,
+            Level::WARN => libc::LOG_WARNING,
+            Level::INFO => libc::LOG_INFO,
+            Level::DEBUG | Level::TRACE => libc::LOG_DEBUG,
+        };
+
+        SyslogWriter { priority }
+    }
+}
+
+impl Write for SyslogWriter {
+    // The fmt layer writes each formatted record with a single call, so every call is one message
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let message = String::from_utf8_lossy(buf);
+
+        let c_string =
+            CString::new(message.trim_end().replace('\0', "")).map_err(io::Error::other)?;
+
+        unsafe {
+            libc::syslog(self.priority, c"%s".as_ptr(), c_string.as_ptr());
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
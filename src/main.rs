@@ -1,64 +1,532 @@
 #![deny(clippy::all)]
 #![warn(clippy::pedantic)]
 
+mod archive;
+mod audit;
+mod backup;
+mod deletion;
+mod diff;
+mod digits;
+mod exec;
+mod exit_code;
+mod expand;
+mod file_system;
+mod git;
+mod hash;
+mod image;
+mod inspect;
+mod lock;
+mod logging;
+mod output;
+mod peek;
+mod plan;
+mod report;
+mod restore;
+mod rpc;
+mod sarif;
+mod schema;
+mod semantic;
+mod signal;
+mod state;
+mod suggest;
+mod summary;
+mod version;
+mod wait;
+
 use anyhow::Context;
-use clap::Parser;
+use audit::AuditLog;
+use clap::{Parser, Subcommand};
+use deletion::{Answers, DeleteOptions, Deletion, DeletionEngine};
+use exit_code::ExitCode;
+use hash::HashAlgorithm;
+use inspect::{DirectoryCounts, InspectOptions, Inspection, PathState};
+use logging::{LogFormat, LogTarget};
+use output::{note, OutputStreams};
 use owo_colors::OwoColorize;
+use report::{OutputFormat, Report, ReportFormatter};
+use semantic::SemanticFormat;
 use std::{
-    env,
-    fs::{self},
-    io::{self, ErrorKind},
-    path::Path,
+    env, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process,
+    sync::LazyLock,
+    time::Duration,
 };
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use summary::RunSummary;
 
 /// Checks if a directory or file is empty, or if a symbolic link points to a path that does not exist. Only supports UTF-8 paths.
-#[derive(Parser)]
-#[command(author, version, about)]
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Parser)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    author,
+    version,
+    about,
+    subcommand_negates_reqs = true
+)]
 struct EmpdArgs {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Delete the file or directory if it is empty
     #[arg(short, long)]
     delete_if_empty: bool,
-    /// Path to test
-    #[arg(index = 1_usize)]
-    path: String,
+    /// Answer "y" to every deletion prompt (without it, deletion is skipped with exit code 25, 35, or 45 when there is no terminal to prompt on)
+    #[arg(conflicts_with = "no", long, requires = "delete_if_empty", short)]
+    yes: bool,
+    /// Answer "n" to every deletion prompt, exiting with the "declined" exit codes (22, 32, or 42), to test scripts and hooks without deleting anything
+    #[arg(conflicts_with = "answers", long, requires = "delete_if_empty")]
+    no: bool,
+    /// Answer each deletion prompt with the next line of this file (e.g. /dev/fd/3), so that automation can drive the prompts without a terminal
+    #[arg(
+        conflicts_with = "yes",
+        long,
+        requires = "delete_if_empty",
+        value_name = "FILE"
+    )]
+    answers: Option<PathBuf>,
+    /// Instead of deleting, append what would be deleted to this file, for `empd apply` to delete after review
+    #[arg(long, requires = "delete_if_empty", value_name = "FILE")]
+    plan: Option<PathBuf>,
+    /// After deleting a directory, also delete each of its ancestors in the given path that is left empty, like `rmdir -p`
+    #[arg(long, requires = "delete_if_empty", short)]
+    parents: bool,
+    /// Exit with 0 rather than 31 when a directory is not deleted because it is not empty, like `rmdir --ignore-fail-on-non-empty`
+    #[arg(long, requires = "delete_if_empty")]
+    ignore_fail_on_non_empty: bool,
+    /// Delete an empty directory even if a file system is mounted on it (which is refused with exit code 34 otherwise)
+    #[arg(long, requires = "delete_if_empty")]
+    allow_mount_point: bool,
+    /// Delete an empty btrfs subvolume or ZFS dataset with `btrfs subvolume delete` or `zfs destroy` (which is refused with exit code 34 otherwise, as rmdir cannot delete them)
+    #[arg(long, requires = "delete_if_empty")]
+    delete_subvolume: bool,
+    /// Only delete paths owned by this user (a name or ID), leaving others alone without failing
+    #[arg(long, requires = "delete_if_empty", value_name = "USER")]
+    owner: Option<String>,
+    /// Only delete paths that you are allowed to delete, leaving others alone without failing
+    #[arg(long, requires = "delete_if_empty")]
+    writable_only: bool,
+    /// Only delete paths that no process has open (or as its working directory), leaving others alone without failing
+    #[arg(long, requires = "delete_if_empty")]
+    skip_in_use: bool,
+    /// After deleting, fsync the parent directory so the deletion is persisted before exiting
+    #[arg(long, requires = "delete_if_empty")]
+    sync: bool,
+    /// Append a JSON record of every deletion, with the permissions, ownership, and modification time the path had, to this file
+    #[arg(long, requires = "delete_if_empty", value_name = "FILE")]
+    audit_log: Option<PathBuf>,
+    /// Add everything deleted, with its permissions, ownership, and modification time, to this tar archive, for `empd restore`
+    #[arg(long, requires = "delete_if_empty", value_name = "FILE")]
+    backup: Option<PathBuf>,
+    /// Send a desktop notification summarizing how many empty items were found and deleted (requires the "notify" feature)
+    #[arg(long)]
+    notify: bool,
+    /// POST a JSON summary of findings and deletions to this URL (e.g. a Slack incoming webhook) (requires the "webhook" feature)
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+    /// Run this command for every empty item found, with "{}" replaced by the item's path (quoted as one argument wherever "{}" appears, so `{}`, `'{}'`, and `"{}"` are equivalent)
+    #[arg(long, value_name = "COMMAND")]
+    exec: Option<String>,
+    /// Run this command once after checking, with "{}" replaced by the paths of all empty items found (each quoted as one argument, as with --exec)
+    #[arg(long, value_name = "COMMAND")]
+    exec0: Option<String>,
+    /// Run this command before each deletion, with `EMPD_PATH` and `EMPD_KIND` set; a non-zero exit status vetoes the deletion
+    #[arg(long, requires = "delete_if_empty", value_name = "COMMAND")]
+    pre_delete_hook: Option<String>,
+    /// Format of log records
+    #[arg(default_value_t = LogFormat::Pretty, long, value_enum)]
+    log_format: LogFormat,
+    /// Write log records to this file, rotated daily, instead of to stderr
+    #[arg(conflicts_with = "log_target", long, value_name = "FILE")]
+    log_file: Option<PathBuf>,
+    /// Where to send log records
+    #[arg(default_value_t = LogTarget::Stderr, long, value_enum)]
+    log_target: LogTarget,
+    /// Hold an advisory lock (flock) on the file or directory from before checking it until after deleting it
+    #[arg(long)]
+    lock: bool,
+    /// Wait until the path is empty (or a symbolic link to a non-existent path) before reporting on it
+    #[arg(long)]
+    wait: bool,
+    /// With --wait, give up after this long (e.g. "30s", "5m") and report on the path as it is
+    #[arg(long, requires = "wait", value_name = "DURATION", value_parser = wait::parse_duration)]
+    timeout: Option<Duration>,
+    /// With --wait, how often to check the path
+    #[arg(default_value = "2s", long, requires = "wait", value_name = "DURATION", value_parser = wait::parse_poll_interval)]
+    poll_interval: Duration,
+    /// Read zero-length files to check if they are empty, as procfs, sysfs, and some FUSE and network file systems report a size of 0 for files with content
+    #[arg(alias = "read-to-verify", long)]
+    verify_read: bool,
+    /// The most that --verify-read reads from each file
+    #[arg(default_value_t = 4_096_u64, long, requires = "verify_read", value_name = "BYTES", value_parser = clap::value_parser!(u64).range(1..))]
+    max_read_bytes: u64,
+    /// For a non-empty directory, also count its files by extension
+    #[arg(long)]
+    by_extension: bool,
+    /// Treat a directory whose only entries are hidden (names starting with ".") as empty, listing the entries that were ignored
+    #[arg(conflicts_with = "delete_if_empty", long)]
+    ignore_hidden: bool,
+    /// Fail if a directory contains sockets, pipes, or devices, rather than counting them as "other" entries
+    #[arg(long)]
+    strict: bool,
+    /// For a non-empty directory, also count all of its descendants by type, and the total size of its files
+    #[arg(long)]
+    recursive_counts: bool,
+    /// For a non-empty directory, also count its descendants at each depth, to show whether its content is shallow or deep
+    #[arg(long)]
+    depth_profile: bool,
+    /// Treat files that contain only NUL bytes (left by preallocation or corruption) as empty, showing their size (only reported, so it cannot be combined with --delete-if-empty)
+    #[arg(conflicts_with = "delete_if_empty", long)]
+    nul_only_is_empty: bool,
+    /// Treat a non-empty file as empty if this command exits successfully for it, e.g. `--empty-when 'test "$(wc -w < {})" = 0'` for whitespace-only files (every "{}" is replaced with the path, quoted as with --exec), which is only reported, so it cannot be combined with --delete-if-empty
+    #[arg(conflicts_with = "delete_if_empty", long, value_name = "COMMAND")]
+    empty_when: Option<String>,
+    /// Treat small files of this format as empty if they hold no data, e.g. "{}", "[]", an empty YAML document, or a CSV file with only a header (only reported, so it cannot be combined with --delete-if-empty)
+    #[arg(conflicts_with = "delete_if_empty", long, value_enum)]
+    semantic: Option<SemanticFormat>,
+    /// Print the hash of a non-empty file, for deduplication or verification
+    #[arg(long, value_enum)]
+    hash: Option<HashAlgorithm>,
+    /// Show the first BYTES (64 if not given) of a non-empty file, as text or a hexdump
+    #[arg(default_missing_value = "64", long, num_args = 0_usize..=1_usize, require_equals = true, value_name = "BYTES")]
+    peek: Option<u64>,
+    /// Show when an empty path was last modified, as stale empty paths are usually the ones worth deleting
+    #[arg(long)]
+    show_mtime: bool,
+    /// Succeed if the path is non-empty, and fail with exit code 10 if it is empty (e.g. to check that a build produced output)
+    #[arg(conflicts_with = "delete_if_empty", long)]
+    invert: bool,
+    /// Behave like `find PATH -maxdepth 0 -empty`: print the path if it is an empty file or directory (symbolic links never are), print nothing otherwise, and exit with 0 unless the path could not be accessed
+    #[arg(conflicts_with_all = ["delete_if_empty", "format", "ignore_hidden", "invert", "output"], long)]
+    find_compat: bool,
+    /// Only print a directory's entry counts, as one line of NAME=COUNT pairs (or JSON with --format json), and exit with 0 whether or not it is empty
+    #[arg(conflicts_with_all = ["delete_if_empty", "find_compat", "invert", "output"], long)]
+    counts_only: bool,
+    /// Record the result in this file, and report if the path became empty or stopped being empty since the last run that used it
+    #[arg(long, value_name = "FILE")]
+    state: Option<PathBuf>,
+    /// Do not expand "~", "~user", "$VAR", and "${VAR}" in the path (a path that exists as given is never expanded)
+    #[arg(long)]
+    no_expand: bool,
+    /// Report the path as it was given, instead of canonicalizing it
+    #[arg(alias = "relative-paths", long)]
+    no_canonicalize: bool,
+    /// Which streams notes, prompts, and what was done go to ("stderr" keeps stdout for results only)
+    #[arg(default_value_t = OutputStreams::Split, long, value_enum)]
+    output_streams: OutputStreams,
+    /// How to report the result on stdout
+    #[arg(default_value_t = OutputFormat::Human, long, value_enum)]
+    format: OutputFormat,
+    /// Separate thousands in counts and byte sizes, with SEPARATOR or, if none is given, the locale's (e.g. "1,234,567")
+    #[arg(default_missing_value = "locale", long, num_args = 0_usize..=1_usize, require_equals = true, value_name = "SEPARATOR")]
+    group_digits: Option<String>,
+    /// Also write the result to this file, replacing it in one step, as JSON (".json") or SARIF (".sarif" or ".sarif.json")
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// Check an open file descriptor inherited from the parent process instead of a path
+    #[arg(conflicts_with_all = ["delete_if_empty", "lock", "path", "wait"], long, value_name = "FD", value_parser = clap::value_parser!(i32).range(0..))]
+    fd: Option<i32>,
+    /// Serve JSON-RPC 2.0 requests ("check" and "delete", each taking a "path" parameter) read line by line from stdin, responding on stdout ("delete" is refused unless --delete-if-empty is also given, and honours the other deletion options)
+    #[arg(conflicts_with_all = ["answers", "ignore_fail_on_non_empty", "no", "parents", "path"], long)]
+    serve_stdio: bool,
+    /// Check each path listed in this file ("-" for standard input), one per line and taken literally (without expansion), in turn, exiting with the first non-zero exit code (with --format json, results are printed one per line)
+    #[arg(conflicts_with_all = ["fd", "output", "path", "serve_stdio"], long, value_name = "FILE")]
+    paths_from: Option<PathBuf>,
+    /// Paths in the --paths-from file are separated by NUL characters rather than newlines (e.g. from `find -print0`)
+    #[arg(long, requires = "paths_from")]
+    null: bool,
+    /// Path to test ("-" for standard input, which is empty if it ends without producing any bytes)
+    #[arg(index = 1_usize, required_unless_present_any = ["fd", "paths_from", "serve_stdio"])]
+    path: Option<String>,
+}
+
+#[derive(Clone, Subcommand)]
+enum Command {
+    /// Delete the paths recorded with --plan, skipping any that are no longer in the state they were planned in
+    Apply {
+        /// Plan file
+        plan: PathBuf,
+        /// After deleting each path, fsync its parent directory
+        #[arg(long)]
+        sync: bool,
+        /// Append a JSON record of every deletion, with the permissions, ownership, and modification time the path had, to this file
+        #[arg(long, value_name = "FILE")]
+        audit_log: Option<PathBuf>,
+        /// Add everything deleted, with its permissions, ownership, and modification time, to this tar archive, for `empd restore`
+        #[arg(long, value_name = "FILE")]
+        backup: Option<PathBuf>,
+    },
+    /// Check if a zip or tar (optionally gzip-compressed) archive contains no entries, or only empty files or directories
+    Archive {
+        /// Archive to inspect
+        path: String,
+    },
+    /// Compare two saved results (--state files or SARIF logs, in any combination), listing empty paths that appeared (+) and disappeared (-), compared as canonical paths
+    Diff {
+        /// Earlier result
+        old: PathBuf,
+        /// Later result
+        new: PathBuf,
+    },
+    /// List every exit code and what it means
+    ExitCodes {
+        /// Print as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report tracked zero-byte files, and tracked directories kept alive only by placeholder files, in a Git repository
+    GitScan {
+        /// Directory in the repository to scan (the current directory by default)
+        #[arg(default_value = ".")]
+        path: String,
+    },
+    /// Run as a Git hook
+    #[command(subcommand)]
+    Hook(HookCommand),
+    /// Report empty directories, zero-byte files, and whiteouts in each layer of a container image
+    ImageScan {
+        /// OCI image layout directory, or archive written by `docker save`
+        path: String,
+    },
+    /// Recreate the empty directories, files, and symbolic links in a --backup archive or --audit-log journal, with their permissions, and where possible, ownership and modification times
+    Restore {
+        /// Backup archive or audit log
+        path: PathBuf,
+    },
+    /// Print the version, Git commit, build date, target, and enabled features
+    Version {
+        /// Print as JSON, for checking deployed builds
+        #[arg(long)]
+        json: bool,
+    },
 }
 
-const CHECK_MARK: &str = "✔️";
-const X: &str = "🗙";
+#[derive(Clone, Subcommand)]
+enum HookCommand {
+    /// Fail if the staged changes introduce empty files (install with `echo 'exec empd hook pre-commit' > .git/hooks/pre-commit`)
+    PreCommit {
+        /// Allow empty files with this name, like ".gitkeep" (can be repeated, and added to with `git config --add empd.placeholder <NAME>`)
+        #[arg(long, value_name = "NAME")]
+        allow_placeholder: Vec<String>,
+    },
+}
 
-fn main() -> Result<(), i32> {
+/// Marker for empty paths, which `EMPD_EMPTY_MARK` overrides (e.g. with a Nerd Font icon, or plain text)
+static CHECK_MARK: LazyLock<String> = LazyLock::new(|| mark("EMPD_EMPTY_MARK", "✔️"));
+/// Marker for non-empty paths, which `EMPD_NON_EMPTY_MARK` overrides
+static X: LazyLock<String> = LazyLock::new(|| mark("EMPD_NON_EMPTY_MARK", "🗙"));
+
+fn main() -> process::ExitCode {
     // TODO
     env::set_var("RUST_BACKTRACE", "1");
     // TODO
     env::set_var("RUST_LOG", "debug");
 
-    tracing_subscriber::registry()
-        .with(EnvFilter::from_default_env())
-        .with(tracing_subscriber::fmt::layer().pretty())
-        .init();
+    let empd_args = EmpdArgs::parse();
+
+    // Keeps stdout parseable (or, for quiet output, empty) unless everything was asked to go there
+    output::set(match (empd_args.format, empd_args.output_streams) {
+        (OutputFormat::Human, ou) => ou,
+        (_, OutputStreams::Split) => OutputStreams::Stderr,
+        (_, ou) => ou,
+    });
+
+    if let Some(st) = &empd_args.group_digits {
+        digits::set(st);
+    }
+
+    let _tracing_guard = match logging::init_tracing(
+        empd_args.log_format,
+        empd_args.log_target,
+        empd_args.log_file.as_deref(),
+    ) {
+        Ok(tr) => tr,
+        Err(er) => {
+            eprintln!("Could not set up logging: {er:#}");
+
+            return process::ExitCode::FAILURE;
+        }
+    };
+
+    let logs_to_stderr =
+        empd_args.log_file.is_none() && matches!(empd_args.log_target, LogTarget::Stderr);
+
+    let result = match empd_args.paths_from.clone() {
+        Some(pa) => start_paths_from(&empd_args, &pa),
+        None => start(empd_args),
+    };
 
-    let result = start();
+    let exit_code = match result {
+        Ok(Ok(())) => 0_i32,
+        Ok(Err(ex)) => ex.code(),
+        Err(er) if er.is::<signal::Interrupted>() => {
+            note!("{er:#}");
+            note!(
+                "Exiting with non-zero exit code {}",
+                ExitCode::Interrupted.bold()
+            );
 
-    match result {
-        Ok(re) => re,
+            ExitCode::Interrupted.code()
+        }
         Err(er) => {
             tracing::error!(
                 backtrace = %er.backtrace(),
                 error = %er,
             );
 
-            Err(1_i32)
+            if !logs_to_stderr {
+                eprintln!("Error: {er:#}");
+            }
+
+            1_i32
         }
-    }
+    };
+
+    // Returning `Err` from `main` would collapse every non-zero exit code to 1
+    process::ExitCode::from(u8::try_from(exit_code).unwrap_or(u8::MAX))
 }
 
 #[allow(clippy::too_many_lines)]
-fn start() -> anyhow::Result<Result<(), i32>> {
+fn start(empd_args: EmpdArgs) -> anyhow::Result<Result<(), ExitCode>> {
+    signal::install_handlers()?;
+
     let EmpdArgs {
+        command,
         delete_if_empty,
+        yes,
+        no,
+        answers,
+        plan,
+        parents,
+        ignore_fail_on_non_empty,
+        allow_mount_point,
+        delete_subvolume,
+        owner,
+        writable_only,
+        skip_in_use,
+        sync,
+        audit_log,
+        backup,
+        notify,
+        webhook,
+        exec,
+        exec0,
+        pre_delete_hook,
+        log_format: _,
+        log_file: _,
+        log_target: _,
+        lock,
+        wait,
+        timeout,
+        poll_interval,
+        verify_read,
+        max_read_bytes,
+        by_extension,
+        ignore_hidden,
+        strict,
+        recursive_counts,
+        depth_profile,
+        nul_only_is_empty,
+        empty_when,
+        semantic,
+        hash,
+        peek,
+        show_mtime,
+        invert,
+        find_compat,
+        counts_only,
+        state,
+        no_expand,
+        no_canonicalize,
+        output_streams: _,
+        format,
+        group_digits: _,
+        output,
+        fd,
+        serve_stdio,
+        paths_from,
+        null: _,
         path,
-    } = EmpdArgs::parse();
+    } = empd_args;
+
+    if let Some(co) = command {
+        let result = match co {
+            Command::Apply {
+                plan,
+                sync,
+                audit_log,
+                backup,
+            } => plan::apply(&plan, sync, audit_log.as_deref(), backup.as_deref())?,
+            Command::Archive { path } => archive::check(&path)?,
+            Command::Diff { old, new } => diff::diff(&old, &new)?,
+            Command::ExitCodes { json } => exit_code::print(json)?,
+            Command::GitScan { path } => git::scan(&path)?,
+            Command::Hook(HookCommand::PreCommit { allow_placeholder }) => {
+                git::pre_commit(&allow_placeholder)?
+            }
+            Command::ImageScan { path } => image::scan(&path)?,
+            Command::Restore { path } => restore::restore(&path)?,
+            Command::Version { json } => version::print(json)?,
+        };
+
+        let result = interrupted_or(result);
+
+        if let Err(ex) = result {
+            note!("Exiting with non-zero exit code {}", ex.bold());
+        }
+
+        return Ok(result);
+    }
+
+    if serve_stdio {
+        let deletion_engine = if delete_if_empty {
+            // Each "delete" request is its own confirmation, as there is no terminal to prompt on
+            let delete_options = DeleteOptions {
+                answers: Answers::Yes,
+                sync,
+                pre_delete_hook: pre_delete_hook.as_deref(),
+                allow_mount_point,
+                delete_subvolume,
+                owner: owner.as_deref().map(audit::user_id).transpose()?,
+                writable_only,
+                skip_in_use,
+                plan: plan.as_deref(),
+                backup: backup.as_deref(),
+            };
+
+            Some(DeletionEngine::new(
+                delete_options,
+                audit_log.as_deref().map(AuditLog::open).transpose()?,
+            ))
+        } else {
+            None
+        };
+
+        return rpc::serve(deletion_engine);
+    }
+
+    let path = match (fd, path) {
+        (Some(it), _) => format!("/dev/fd/{it}"),
+        (None, Some(st)) if no_expand => st,
+        // E.g. a file that is literally named "$HOME"
+        (None, Some(st)) if Path::new(&st).symlink_metadata().is_ok() => st,
+        (None, Some(st)) => {
+            let expanded = expand::expand(&st)?;
+
+            if expanded != st {
+                note!(
+                    "Expanded input path \"{}\" to \"{}\"",
+                    st.bold(),
+                    expanded.bold()
+                );
+            }
+
+            expanded
+        }
+        (None, None) => anyhow::bail!("No path to test was given"),
+    };
+
+    let _span = tracing::info_span!("check", path = %path).entered();
 
     let path_path = Path::new(&path);
 
@@ -66,287 +534,512 @@ fn start() -> anyhow::Result<Result<(), i32>> {
         .to_str()
         .context("Could not convert path to a UTF-8 string")?;
 
-    let symlink_metadata_result = fs::symlink_metadata(path_path);
+    if format != OutputFormat::Human
+        && (notify || webhook.is_some() || exec.is_some() || exec0.is_some())
+    {
+        anyhow::bail!("JSON, SARIF, CSV, and quiet output only report the result, so they cannot be combined with notifying or running commands");
+    }
 
-    let result = match symlink_metadata_result {
-        Err(er) => match er.kind() {
-            ErrorKind::NotFound => {
-                eprintln!("Path \"{}\" does not exist", path_path_str.bold());
+    if notify && !cfg!(feature = "notify") {
+        anyhow::bail!("This build of empd cannot send desktop notifications, as it was built without the \"notify\" feature");
+    }
 
-                Err(11_i32)
-            }
-            ErrorKind::PermissionDenied => {
-                eprintln!("Permission to path \"{}\" was denied", path_path_str.bold());
+    if webhook.is_some() && !cfg!(feature = "webhook") {
+        anyhow::bail!("This build of empd cannot post to webhooks, as it was built without the \"webhook\" feature");
+    }
 
-                Err(12_i32)
-            }
-            _ => {
-                anyhow::bail!(er);
-            }
+    if format == OutputFormat::Sarif && delete_if_empty {
+        anyhow::bail!(
+            "SARIF output cannot report deletions, so it cannot be combined with --delete-if-empty"
+        );
+    }
+
+    if let Some(pa) = &output {
+        if OutputFormat::from_extension(pa).is_none() {
+            anyhow::bail!(
+                "Could not infer the format of \"{}\" from its extension (expected \".json\" or \".sarif\")",
+                pa.display()
+            );
+        }
+    }
+
+    let mut warnings = Warnings {
+        print: format != OutputFormat::Json,
+        messages: Vec::new(),
+    };
+
+    if path_path_str == "-" && (delete_if_empty || lock || wait) {
+        anyhow::bail!("Standard input cannot be deleted, locked, or waited on");
+    }
+
+    // A file descriptor's path, or standard input, cannot be reopened to --peek at it
+    let formatter = format.formatter(
+        if fd.is_some() || path_path_str == "-" {
+            None
+        } else {
+            peek
         },
-        Ok(me) => {
-            match me {
-                me if me.is_dir() => {
-                    let canonicalize_result = canonicalize(path_path_str, path_path)?
-                        .context("Could not canonicalize directory path")?;
-
-                    let read_dir = path_path.read_dir().context("Could not read directory")?;
-
-                    let mut directories = 0_u32;
-                    let mut files = 0_u32;
-                    let mut symlinks = 0_u32;
-
-                    for re in read_dir {
-                        let di = re.context("Could not access directory entry")?;
-
-                        let fi = di
-                            .file_type()
-                            .context("Could not get the directory entry's file type")?;
-
-                        match fi {
-                            fi if fi.is_dir() => {
-                                directories += 1_u32;
-                            }
-                            fi if fi.is_file() => {
-                                files += 1_u32;
-                            }
-                            fi if fi.is_symlink() => {
-                                symlinks += 1_u32;
-                            }
-                            _ => {
-                                anyhow::bail!(
-                                    "Encountered directory entry that is not a directory, file, or symlink"
-                                );
-                            }
-                        }
-                    }
-
-                    let total_items = directories + files + symlinks;
-
-                    if total_items > 0_u32 {
-                        println!(
-                            " {}  Path \"{}\" is a {} (directories: {}, files: {}, symlinks: {}, total items: {})",
-                            X.bold().red(),
-                            canonicalize_result.bold(),
-                            "non-empty directory".bold().red(),
-                            bold_if_greater_than_zero(directories),
-                            bold_if_greater_than_zero(files),
-                            bold_if_greater_than_zero(symlinks),
-                            bold_if_greater_than_zero(total_items)
-                        );
-
-                        Err(31_i32)
-                    } else {
-                        println!(
-                            " {}  Path \"{}\" is an {}",
-                            CHECK_MARK.bold().green(),
-                            canonicalize_result.bold(),
-                            "empty directory".bold().green()
-                        );
-
-                        if delete_if_empty {
-                            eprintln!(
-                                "Are you sure you want to delete empty directory \"{}\"? (\"y\")\n\
-                                (Note that no file locking or revalidation is performed, and the directory may be non-empty by the time you respond to this prompt!)",
-                                canonicalize_result.bold()
-                            );
-
-                            let input = &mut String::new();
-
-                            io::stdin().read_line(input)?;
-
-                            if input == "y\n" {
-                                // TODO Status of path could have changed by now
-                                fs::remove_dir(path_path)?;
-
-                                println!(
-                                    "Deleted empty directory \"{}\"",
-                                    canonicalize_result.bold()
-                                );
-
-                                Ok(())
-                            } else {
-                                println!("Input was not \"y\", not deleting empty directory");
-
-                                Err(32_i32)
-                            }
-                        } else {
-                            Ok(())
-                        }
-                    }
-                }
-                me if me.is_file() => {
-                    let canonicalize_result = canonicalize(path_path_str, path_path)?
-                        .context("Could not canonicalize file path")?;
-
-                    let len = me.len();
-
-                    if len > 0_u64 {
-                        println!(
-                            " {}  Path \"{}\" is a {} (bytes: {})",
-                            X.bold().red(),
-                            canonicalize_result.bold(),
-                            "non-empty file".bold().red(),
-                            len.bold()
-                        );
-
-                        Err(21_i32)
-                    } else {
-                        println!(
-                            " {}  Path \"{}\" is an {}",
-                            CHECK_MARK.bold().green(),
-                            canonicalize_result.bold(),
-                            "empty file".bold().green()
-                        );
-
-                        if delete_if_empty {
-                            eprintln!(
-                                "Are you sure you want to delete empty file \"{}\"? (\"y\")\n\
-                                (Note that no file locking or revalidation is performed, and the file may be non-empty by the time you respond to this prompt!)",
-                                canonicalize_result.bold()
-                            );
-
-                            let input = &mut String::new();
-
-                            io::stdin().read_line(input)?;
-
-                            if input == "y\n" {
-                                // TODO Status of path could have changed by now
-                                fs::remove_file(path_path)?;
-
-                                println!("Deleted empty file \"{}\"", canonicalize_result.bold());
-
-                                Ok(())
-                            } else {
-                                println!("Input was not \"y\", not deleting empty file");
-
-                                Err(22_i32)
-                            }
-                        } else {
-                            Ok(())
-                        }
-                    }
-                }
-                me if me.is_symlink() => {
-                    let link_path_buf = path_path
-                        .read_link()
-                        .context("Could not read symbolic link")?;
-
-                    let link_path_buf_str = link_path_buf
-                        .to_str()
-                        .context("Could not convert symbolic link path to a UTF-8 string")?;
-
-                    let canonicalize_result = canonicalize(path_path_str, path_path)?;
-
-                    #[allow(clippy::single_match_else)]
-                    {
-                        match canonicalize_result {
-                            Some(st) => {
-                                println!(
-                                    " {}  Path \"{}\" (non-canonicalized) is a symbolic link to \"{}\" (resolves to \"{st}\")",
-                                    X.bold().red(),
-                                    path_path_str.bold(),
-                                    link_path_buf_str.bold()
-                                );
-
-                                Err(41_i32)
-                            }
-                            None => {
-                                println!(
-                                    " {}  Path \"{}\" (non-canonicalized) is a symbolic link to non-existent file \"{}\" (non-canonicalized)",
-                                    CHECK_MARK.bold().green(),
-                                    path_path_str.bold(),
-                                    link_path_buf_str.bold()
-                                );
-
-                                if delete_if_empty {
-                                    eprintln!(
-                                        "Are you sure you want to delete symbolic link \"{}\" (non-canonicalized) pointing to non-existent file \"{}\"? (non-canonicalized) (\"y\")\n\
-                                        (Note that no file locking or revalidation is performed, and the symbolic link destination may exist by the time you respond to this prompt!)",
-                                        path_path_str.bold(),
-                                        link_path_buf_str.bold()
-                                    );
-
-                                    let input = &mut String::new();
-
-                                    io::stdin().read_line(input)?;
-
-                                    if input == "y\n" {
-                                        // TODO
-                                        // Status of path could have changed by now
-                                        fs::remove_file(path_path)?;
-
-                                        println!(
-                                            "Deleted symbolic link \"{}\" (non-canonicalized)",
-                                            path_path_str.bold()
-                                        );
-
-                                        Ok(())
-                                    } else {
-                                        println!("Input was not \"y\", not deleting symbolic link");
-
-                                        Err(42_i32)
-                                    }
-                                } else {
-                                    Ok(())
-                                }
-                            }
-                        }
-                    }
-                }
-                _ => {
-                    anyhow::bail!("Path \"{path_path_str}\" is not a directory, file, or symlink")
+        paths_from.is_some(),
+    );
+
+    let mut empty_findings = Vec::<String>::new();
+    let mut empty_items_deleted = 0_usize;
+    let mut deletion = Deletion::NotAttempted;
+
+    let delete_options = DeleteOptions {
+        answers: match (yes, no, &answers) {
+            (true, _, _) => Answers::Yes,
+            (false, true, _) => Answers::No,
+            (false, false, Some(pa)) => Answers::File(pa),
+            (false, false, None) => Answers::Prompt,
+        },
+        sync,
+        pre_delete_hook: pre_delete_hook.as_deref(),
+        allow_mount_point,
+        delete_subvolume,
+        owner: owner.as_deref().map(audit::user_id).transpose()?,
+        writable_only,
+        skip_in_use,
+        plan: plan.as_deref(),
+        backup: backup.as_deref(),
+    };
+
+    let mut deletion_engine = DeletionEngine::new(
+        delete_options,
+        audit_log.as_deref().map(AuditLog::open).transpose()?,
+    );
+
+    // Held until the end of the run, across any confirmation prompt and deletion
+    let _path_lock = if lock {
+        lock::lock(path_path_str)?
+    } else {
+        None
+    };
+
+    let inspect_options = InspectOptions {
+        verify_read,
+        max_read_bytes,
+        by_extension,
+        ignore_hidden,
+        strict,
+        recursive_counts,
+        depth_profile,
+        nul_only_is_empty,
+        empty_when,
+        semantic,
+        hash,
+        show_mtime,
+    };
+
+    let inspection = if let Some(it) = fd {
+        inspect::inspect_fd(it, path_path_str)?
+    } else if path_path_str == "-" {
+        inspect::inspect_fd(libc::STDIN_FILENO, path_path_str)?
+    } else if wait {
+        wait::wait_until_empty(path_path_str, &inspect_options, timeout, poll_interval)?
+    } else {
+        Some(inspect::inspect(path_path_str, &inspect_options)?)
+    };
+
+    let Some(inspection) = inspection else {
+        note!(
+            "Interrupted by {}",
+            signal::received().unwrap_or_default().bold()
+        );
+        note!(
+            "Exiting with non-zero exit code {}",
+            ExitCode::Interrupted.bold()
+        );
+
+        return Ok(Err(ExitCode::Interrupted));
+    };
+
+    if find_compat {
+        return Ok(interrupted_or(report_like_find(&inspection, path_path_str)));
+    }
+
+    if counts_only {
+        return print_counts(&inspection, formatter.as_ref()).map(interrupted_or);
+    }
+
+    // Only dangling symbolic links lack a canonical path, and they are reported by their input path
+    let canonical_path_str = if no_canonicalize {
+        path_path_str
+    } else {
+        print_canonicalization(&inspection, &mut warnings);
+
+        inspection
+            .canonical_path
+            .as_deref()
+            .unwrap_or(path_path_str)
+    };
+
+    if let Some(st) = inspection.pseudo_file_system {
+        if !verify_read {
+            warnings.push(&format!(
+                "Warning: path \"{}\" is on {}, where files report a size of 0 even when they have content (use --verify-read to read it instead)",
+                canonical_path_str.bold(),
+                st.bold()
+            ));
+        }
+    }
+
+    if let (PathState::NonEmptyFile { bytes }, Some(me)) = (&inspection.state, &inspection.metadata)
+    {
+        if me.len() == 0_u64 {
+            warnings.push(&format!(
+                "Path \"{}\" reports a size of 0, but {} bytes were read from it{}",
+                canonical_path_str.bold(),
+                digits::group(*bytes).bold(),
+                if *bytes == max_read_bytes {
+                    " (stopped at --max-read-bytes)"
+                } else {
+                    ""
                 }
+            ));
+        }
+    }
+
+    if let PathState::NonEmptyDirectory(directory_counts) = &inspection.state {
+        for st in &directory_counts.entry_errors {
+            warnings.push(&format!("Warning: {st}"));
+        }
+    }
+
+    if !inspection.ignored_hidden_entries.is_empty() {
+        warnings.push(&format!(
+            "Ignored {} hidden entries: {}",
+            inspection.ignored_hidden_entries.len().bold(),
+            inspection.ignored_hidden_entries.join(", ")
+        ));
+    }
+
+    if let Some(pa) = &state {
+        state::update(pa, &inspection)?;
+    }
+
+    let report = Report {
+        inspection: &inspection,
+        path_str: path_path_str,
+        canonical_path_str,
+        warnings: &warnings.messages,
+    };
+
+    formatter.inspected(&mut io::stdout(), &report)?;
+
+    if inspection.is_empty() {
+        // Dangling symbolic links and streams have no canonical path, so they are shown as given
+        let shown_path_str = match inspection.state {
+            PathState::DanglingSymlink { .. } | PathState::EmptyStream => path_path_str,
+            _ => canonical_path_str,
+        };
+
+        empty_findings.push(shown_path_str.to_owned());
+
+        if let Some(st) = &exec {
+            exec::exec_for_finding(st, shown_path_str)?;
+        }
+
+        if delete_if_empty {
+            deletion = deletion_engine.delete(&inspection, shown_path_str)?;
+
+            if deletion == Deletion::Deleted {
+                empty_items_deleted += 1_usize;
             }
         }
+    }
+
+    let mut result = inspection
+        .result()
+        .and_then(|()| deletion.result(&inspection));
+
+    if parents && deletion == Deletion::Deleted {
+        result = deletion_engine.delete_parents(&inspection)?;
+    }
+
+    if ignore_fail_on_non_empty && result == Err(ExitCode::NonEmptyDirectory) {
+        result = Ok(());
+    }
+
+    if invert {
+        result = invert_result(result, &inspection);
+    }
+
+    formatter.finished(&mut io::stdout(), &report, deletion)?;
+
+    if let Some(pa) = &output {
+        report::write_output(pa, &report, deletion)?;
+    }
+
+    if let Some(st) = &exec0 {
+        if !empty_findings.is_empty() {
+            exec::exec_for_findings(st, &empty_findings)?;
+        }
+    }
+
+    let result = interrupted_or(result);
+
+    let run_summary = RunSummary::new(
+        path_path_str,
+        empty_findings.len(),
+        empty_items_deleted,
+        result.err().map_or(0_i32, ExitCode::code),
+    );
+
+    if let Some(st) = signal::received() {
+        note!("Interrupted by {}. {}", st.bold(), run_summary.text);
+    }
+
+    #[cfg(feature = "notify")]
+    if notify {
+        summary::send_desktop_notification(&run_summary);
+    }
+
+    #[cfg(feature = "webhook")]
+    if let Some(st) = &webhook {
+        summary::post_webhook(st, &run_summary);
+    }
+
+    if let Err(ex) = result {
+        note!("Exiting with non-zero exit code {}", ex.bold());
+    }
+
+    Ok(result)
+}
+
+/// Loops stop early when SIGINT or SIGTERM arrives, so a run that received one reports being
+/// interrupted instead of its incomplete result
+fn interrupted_or(result: Result<(), ExitCode>) -> Result<(), ExitCode> {
+    if signal::received().is_some() {
+        Err(ExitCode::Interrupted)
+    } else {
+        result
+    }
+}
+
+/// For `--paths-from`: checks each listed path as if it had been given on its own
+fn start_paths_from(
+    empd_args: &EmpdArgs,
+    paths_from: &Path,
+) -> anyhow::Result<Result<(), ExitCode>> {
+    let reads_stdin = paths_from == Path::new("-");
+
+    // Confirmation prompts are answered on standard input too
+    if reads_stdin && empd_args.delete_if_empty {
+        anyhow::bail!("Paths cannot be read from standard input when deleting");
+    }
+
+    let contents = if reads_stdin {
+        let mut contents = Vec::<u8>::new();
+
+        io::stdin()
+            .read_to_end(&mut contents)
+            .context("Could not read paths from standard input")?;
+
+        contents
+    } else {
+        fs::read(paths_from)
+            .with_context(|| format!("Could not read paths from \"{}\"", paths_from.display()))?
     };
 
-    if let Err(it) = result {
-        eprintln!("Exiting with non-zero exit code {}", it.bold());
+    let separator = if empd_args.null { b'\0' } else { b'\n' };
+
+    let mut result = Ok(());
+
+    for sl in contents
+        .split(|ue| *ue == separator)
+        .filter(|sl| !sl.is_empty())
+    {
+        let path = String::from_utf8(sl.to_vec())
+            .context("Could not convert path in --paths-from file to a UTF-8 string")?;
+
+        // Listed paths are taken literally, and `paths_from` is left set so that JSON results are
+        // printed one per line
+        let path_result = start(EmpdArgs {
+            no_expand: true,
+            path: Some(path),
+            ..empd_args.clone()
+        })?;
+
+        if result.is_ok() {
+            result = path_result;
+        }
+
+        if signal::received().is_some() {
+            return Ok(Err(ExitCode::Interrupted));
+        }
     }
 
     Ok(result)
 }
 
+/// For `--counts-only`, e.g. `directories=1 files=2 symlinks=0 other=0 total_items=3 hidden=1 unreadable=0 vanished=0`
+fn print_counts(
+    inspection: &Inspection,
+    formatter: &dyn ReportFormatter,
+) -> anyhow::Result<Result<(), ExitCode>> {
+    let empty_directory_counts = DirectoryCounts::default();
+
+    let directory_counts = match &inspection.state {
+        PathState::NonEmptyDirectory(di) => di,
+        PathState::EmptyDirectory => &empty_directory_counts,
+        PathState::NotFound => {
+            note!("Path \"{}\" does not exist", inspection.path.bold());
+
+            return Ok(Err(ExitCode::NotFound));
+        }
+        PathState::PermissionDenied => {
+            note!(
+                "Permission to path \"{}\" was denied",
+                inspection.path.bold()
+            );
+
+            return Ok(Err(ExitCode::PermissionDenied));
+        }
+        _ => {
+            note!(
+                "Path \"{}\" is not a directory, so it has no counts",
+                inspection.path.bold()
+            );
+
+            return Ok(Err(ExitCode::Failure));
+        }
+    };
+
+    formatter.counts(&mut io::stdout(), directory_counts)?;
+
+    Ok(Ok(()))
+}
+
+/// For `--find-compat`, with the same output and exit statuses as GNU find
+fn report_like_find(inspection: &Inspection, path_str: &str) -> Result<(), ExitCode> {
+    match inspection.state {
+        PathState::EmptyDirectory | PathState::EmptyFile => {
+            println!("{path_str}");
+
+            Ok(())
+        }
+        PathState::NotFound => {
+            note!("empd: '{path_str}': No such file or directory");
+
+            Err(ExitCode::Failure)
+        }
+        PathState::PermissionDenied => {
+            note!("empd: '{path_str}': Permission denied");
+
+            Err(ExitCode::Failure)
+        }
+        // find only follows the path's components, not the path itself
+        PathState::SymlinkLoop { .. } if inspection.metadata.is_none() => {
+            note!("empd: '{path_str}': Too many levels of symbolic links");
+
+            Err(ExitCode::Failure)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// For `--invert`: non-empty paths succeed, and empty ones fail with exit code 10
+fn invert_result(result: Result<(), ExitCode>, inspection: &Inspection) -> Result<(), ExitCode> {
+    match (result, inspection.is_empty()) {
+        (Ok(()), true) => Err(ExitCode::Empty),
+        (Err(ExitCode::NonEmptyFile | ExitCode::NonEmptyDirectory | ExitCode::Symlink), false) => {
+            Ok(())
+        }
+        (re, _) => re,
+    }
+}
+
+fn mark(key: &str, default: &str) -> String {
+    env::var(key).unwrap_or_else(|_| default.to_owned())
+}
+
 fn bold_if_greater_than_zero(input: u32) -> String {
     if input > 0_u32 {
-        input.bold().to_string()
+        digits::group(input).bold().to_string()
     } else {
         input.to_string()
     }
 }
 
-fn canonicalize(path_str: &str, path_path: &Path) -> anyhow::Result<Option<String>> {
-    let canonicalize_result = fs::canonicalize(path_path);
+/// Non-fatal problems, which are kept for JSON reports, and printed unless JSON is printed
+struct Warnings {
+    print: bool,
+    messages: Vec<String>,
+}
 
-    let option = match canonicalize_result {
-        Ok(pa) => {
-            let path_buf_str = pa
-                .to_str()
-                .context("Could not convert path to a UTF-8 string")?;
+impl Warnings {
+    fn push(&mut self, message: &str) {
+        if self.print {
+            note!("{message}");
+        }
 
-            eprintln!(
+        self.messages.push(strip_ansi_escapes(message));
+    }
+}
+
+/// Removes the color codes that `OwoColorize` adds
+fn strip_ansi_escapes(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+
+    let mut chars = input.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            // Skip to the end of the "ESC [ ... m" sequence
+            chars.by_ref().find(|&ch| ch == 'm');
+        } else {
+            output.push(ch);
+        }
+    }
+
+    output
+}
+
+fn print_canonicalization(inspection: &Inspection, warnings: &mut Warnings) {
+    match (&inspection.canonical_path, &inspection.state) {
+        (
+            _,
+            PathState::NotFound
+            | PathState::PermissionDenied
+            | PathState::EmptyStream
+            | PathState::NonEmptyStream { .. }
+            | PathState::SymlinkLoop { .. },
+        ) => {}
+        (Some(st), _) => {
+            note!(
                 "Canonicalized input path \"{}\" to \"{}\"",
-                path_str.bold(),
-                path_buf_str.bold()
+                inspection.path.bold(),
+                st.bold()
             );
-
-            Some(path_buf_str.to_owned())
         }
-        Err(er) => match er.kind() {
-            ErrorKind::NotFound => {
-                eprintln!(
-                        "Could not canonicalize input path \"{}\" because it or the file it resolves to does not exist",
-                        path_str.bold()
-                    );
-
-                None
-            }
-            _ => {
-                anyhow::bail!(er);
-            }
+        (None, _) => match &inspection.broken_component {
+            Some(br) => warnings.push(&format!(
+                "Could not canonicalize input path \"{}\" because {br}",
+                inspection.path.bold()
+            )),
+            None => warnings.push(&format!(
+                "Could not canonicalize input path \"{}\" because it or the file it resolves to does not exist",
+                inspection.path.bold()
+            )),
         },
-    };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(option)
+    #[test]
+    fn strip_ansi_escapes_removes_color_codes() {
+        assert_eq!(
+            strip_ansi_escapes(&format!(
+                "Path \"{}\" is {}",
+                "a".bold(),
+                "empty".bold().green()
+            )),
+            "Path \"a\" is empty"
+        );
+        assert_eq!(strip_ansi_escapes("no codes"), "no codes");
+    }
 }
@@ -0,0 +1,72 @@
+//! Which stream commentary goes to, so that scripts can keep stdout for results only
+
+use clap::ValueEnum;
+use std::{
+    fmt,
+    io::{self, IsTerminal},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+#[derive(Clone, Copy, ValueEnum)]
+#[repr(u8)]
+pub enum OutputStreams {
+    /// Notes and prompts on stderr; results and what was done on stdout
+    Split,
+    /// Everything on stdout
+    Stdout,
+    /// Only results on stdout, everything else on stderr
+    Stderr,
+}
+
+static OUTPUT_STREAMS: AtomicU8 = AtomicU8::new(OutputStreams::Split as u8);
+
+pub fn set(output_streams: OutputStreams) {
+    OUTPUT_STREAMS.store(output_streams as u8, Ordering::Relaxed);
+}
+
+fn get() -> OutputStreams {
+    match OUTPUT_STREAMS.load(Ordering::Relaxed) {
+        1_u8 => OutputStreams::Stdout,
+        2_u8 => OutputStreams::Stderr,
+        _ => OutputStreams::Split,
+    }
+}
+
+/// Prints a note, warning, or prompt, which goes to stderr unless everything goes to stdout
+pub fn print_note(arguments: fmt::Arguments) {
+    match get() {
+        OutputStreams::Stdout => println!("{arguments}"),
+        OutputStreams::Split | OutputStreams::Stderr => eprintln!("{arguments}"),
+    }
+}
+
+/// Whether notes, including prompts, are shown on a terminal
+pub fn notes_are_terminal() -> bool {
+    match get() {
+        OutputStreams::Stdout => io::stdout().is_terminal(),
+        OutputStreams::Split | OutputStreams::Stderr => io::stderr().is_terminal(),
+    }
+}
+
+/// Prints what was done (e.g. a deletion), which goes to stdout unless only results do
+pub fn print_status(arguments: fmt::Arguments) {
+    match get() {
+        OutputStreams::Stderr => eprintln!("{arguments}"),
+        OutputStreams::Split | OutputStreams::Stdout => println!("{arguments}"),
+    }
+}
+
+macro_rules! note {
+    ($($argument:tt)*) => {
+        $crate::output::print_note(format_args!($($argument)*))
+    };
+}
+
+macro_rules! status {
+    ($($argument:tt)*) => {
+        $crate::output::print_status(format_args!($($argument)*))
+    };
+}
+
+pub(crate) use note;
+pub(crate) use status;
@@ -0,0 +1,67 @@
+//! The start of a non-empty file, so that it is clear at a glance whether its content matters
+
+use anyhow::Context;
+use std::{fmt::Write, fs::File, io::Read, path::Path};
+
+const BYTES_PER_LINE: usize = 16_usize;
+
+/// The first `max_bytes` of the file as lines of text if they are printable UTF-8, or else as a
+/// hexdump like `hexdump -C`'s
+pub fn lines(path_path: &Path, max_bytes: u64) -> anyhow::Result<Vec<String>> {
+    let mut buffer = Vec::<u8>::new();
+
+    File::open(path_path)
+        .context("Could not open file to peek at it")?
+        .take(max_bytes)
+        .read_to_end(&mut buffer)
+        .context("Could not read file to peek at it")?;
+
+    // A multi-byte character cut off at `max_bytes` does not make the rest binary
+    let text = match std::str::from_utf8(&buffer) {
+        Ok(st) => Some(st),
+        Err(er) if er.error_len().is_none() => {
+            std::str::from_utf8(&buffer[..er.valid_up_to()]).ok()
+        }
+        Err(_) => None,
+    };
+
+    if let Some(st) = text.filter(|st| {
+        st.chars()
+            .all(|ch| !ch.is_control() || matches!(ch, '\n' | '\r' | '\t'))
+    }) {
+        return Ok(st.lines().map(|st| format!("| {st}")).collect());
+    }
+
+    let lines = buffer
+        .chunks(BYTES_PER_LINE)
+        .enumerate()
+        .map(|(us, sl)| {
+            let mut line = format!("{:08x} ", us * BYTES_PER_LINE);
+
+            for ue in sl {
+                let _ = write!(line, " {ue:02x}");
+            }
+
+            for _ in sl.len()..BYTES_PER_LINE {
+                line.push_str("   ");
+            }
+
+            let printable = sl
+                .iter()
+                .map(|ue| {
+                    if ue.is_ascii_graphic() || *ue == b' ' {
+                        char::from(*ue)
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>();
+
+            let _ = write!(line, "  |{printable}|");
+
+            line
+        })
+        .collect();
+
+    Ok(lines)
+}
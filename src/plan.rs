@@ -0,0 +1,131 @@
+//! Two-phase deletion for change-controlled environments: `--plan` records what would be deleted
+//! instead of deleting it, and `empd apply` deletes it later, after checking that it is still empty
+
+use crate::{
+    audit::AuditLog,
+    deletion::{Answers, DeleteOptions, DeletionEngine},
+    exit_code::ExitCode,
+    inspect::{self, InspectOptions, Inspection},
+    output::note,
+    schema, signal,
+};
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{self, Path},
+};
+
+/// One line of a plan file
+#[derive(Deserialize, Serialize)]
+struct PlanRecord {
+    #[serde(skip_deserializing)]
+    schema_version: u32,
+    /// Absolute, so that the plan can be applied from any directory
+    path: String,
+    /// E.g. `empty_directory`
+    state: String,
+}
+
+/// Appends a record of deleting `inspection`'s path to the plan file at `plan_path`
+pub fn record(plan_path: &Path, inspection: &Inspection) -> anyhow::Result<()> {
+    let path = match &inspection.canonical_path {
+        Some(st) => st.clone(),
+        // Dangling symbolic links have no canonical path
+        None => path::absolute(&inspection.path)?
+            .to_str()
+            .context("Could not convert path to a UTF-8 string")?
+            .to_owned(),
+    };
+
+    let plan_record = PlanRecord {
+        schema_version: schema::SCHEMA_VERSION,
+        path,
+        state: inspection.state_name()?,
+    };
+
+    let mut line = serde_json::to_string(&plan_record)?;
+
+    line.push('\n');
+
+    OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(plan_path)
+        .context("Could not open plan file")?
+        .write_all(line.as_bytes())
+        .context("Could not write to plan file")?;
+
+    Ok(())
+}
+
+/// Deletes every path in the plan file at `plan_path` that is in the same state as when it was
+/// planned, carrying on past the others and exiting with the first non-zero exit code
+pub fn apply(
+    plan_path: &Path,
+    sync: bool,
+    audit_log_path: Option<&Path>,
+    backup_path: Option<&Path>,
+) -> anyhow::Result<Result<(), ExitCode>> {
+    let plan = fs::read_to_string(plan_path).context("Could not read plan file")?;
+
+    let delete_options = DeleteOptions {
+        // The plan is the confirmation
+        answers: Answers::Yes,
+        sync,
+        pre_delete_hook: None,
+        allow_mount_point: false,
+        delete_subvolume: false,
+        owner: None,
+        writable_only: false,
+        skip_in_use: false,
+        plan: None,
+        backup: backup_path,
+    };
+
+    let mut deletion_engine = DeletionEngine::new(
+        delete_options,
+        audit_log_path.map(AuditLog::open).transpose()?,
+    );
+
+    let mut result = Ok(());
+
+    for (us, st) in plan.lines().enumerate().filter(|(_, st)| !st.is_empty()) {
+        // The remaining records are left undeleted
+        if signal::received().is_some() {
+            return Ok(Err(ExitCode::Interrupted));
+        }
+
+        let plan_record = serde_json::from_str::<PlanRecord>(st).with_context(|| {
+            format!(
+                "Line {} of the plan file is not a plan record",
+                us + 1_usize
+            )
+        })?;
+
+        let inspection = inspect::inspect(&plan_record.path, &InspectOptions::default())?;
+
+        let state = inspection.state_name()?;
+
+        let item_result = if state == plan_record.state {
+            let deletion = deletion_engine.delete(&inspection, &plan_record.path)?;
+
+            deletion.result(&inspection)
+        } else {
+            note!(
+                "Not deleting \"{}\", as it is now {} rather than {}",
+                plan_record.path.bold(),
+                state.bold(),
+                plan_record.state
+            );
+
+            inspection.result().and(Err(ExitCode::Failure))
+        };
+
+        result = result.and(item_result);
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,886 @@
+//! Rendering the result of checking a path, in whichever `--format` was asked for
+
+use crate::{
+    bold_if_greater_than_zero,
+    deletion::Deletion,
+    digits, file_system,
+    file_system::MountPoint,
+    inspect::{DirectoryCounts, Inspection, ItemKind, PathState},
+    output::note,
+    peek, sarif, schema, suggest, CHECK_MARK, X,
+};
+use anyhow::Context;
+use clap::ValueEnum;
+use owo_colors::OwoColorize;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Colored, one line per path
+    Human,
+    /// The result as a JSON object on stdout, with warnings in its "warnings" array instead of on stderr
+    Json,
+    /// A SARIF 2.1.0 log on stdout
+    Sarif,
+    /// One CSV record on stdout: path, state, empty, and deletion (with --counts-only, the counts, in the order --counts-only prints them)
+    Csv,
+    /// Nothing on stdout, leaving the exit code to report the result
+    Quiet,
+}
+
+impl OutputFormat {
+    /// Infers the format of a report file from its extension (".json", or ".sarif")
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" if path.to_string_lossy().ends_with(".sarif.json") => Some(Self::Sarif),
+            "json" => Some(Self::Json),
+            "sarif" => Some(Self::Sarif),
+            _ => None,
+        }
+    }
+
+    /// `peek` is how many bytes of a non-empty file to show, for `--peek`. With `lines`, JSON and
+    /// SARIF results are printed on one line each, so that several results form JSON Lines.
+    pub fn formatter(self, peek: Option<u64>, lines: bool) -> Box<dyn ReportFormatter> {
+        match self {
+            Self::Human => Box::new(Human { peek }),
+            Self::Json => Box::new(Json { lines }),
+            Self::Sarif => Box::new(Sarif { lines }),
+            Self::Csv => Box::new(Csv),
+            Self::Quiet => Box::new(Quiet),
+        }
+    }
+}
+
+/// What there is to report about a checked path
+pub struct Report<'a> {
+    pub inspection: &'a Inspection,
+    /// The path as given (after expansion)
+    pub path_str: &'a str,
+    /// The path as reported, which is the given path if it could not be canonicalized or
+    /// `--no-canonicalize` was used
+    pub canonical_path_str: &'a str,
+    /// Without color codes
+    pub warnings: &'a [String],
+}
+
+/// Renders a [`Report`] to a writer, which is stdout outside of tests. Notes, prompts, and what was
+/// done are printed separately, with `note!` and `status!`.
+pub trait ReportFormatter {
+    /// Called once the path has been inspected, before anything is deleted
+    fn inspected(&self, _writer: &mut dyn Write, _report: &Report) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once any deletion has been attempted
+    fn finished(
+        &self,
+        _writer: &mut dyn Write,
+        _report: &Report,
+        _deletion: Deletion,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// For `--counts-only`
+    fn counts(
+        &self,
+        writer: &mut dyn Write,
+        directory_counts: &DirectoryCounts,
+    ) -> anyhow::Result<()>;
+}
+
+struct Human {
+    peek: Option<u64>,
+}
+
+impl ReportFormatter for Human {
+    fn inspected(&self, writer: &mut dyn Write, report: &Report) -> anyhow::Result<()> {
+        let Report {
+            inspection,
+            path_str,
+            canonical_path_str,
+            ..
+        } = *report;
+
+        match &inspection.state {
+            PathState::NotFound => {
+                note!(
+                    "Path \"{}\" does not exist{}",
+                    path_str.bold(),
+                    describe_broken_component(inspection)
+                );
+
+                for st in suggest::suggestions(path_str) {
+                    note!("    Did you mean \"{}\"?", st.bold());
+                }
+            }
+            PathState::PermissionDenied => {
+                note!(
+                    "Permission to path \"{}\" was denied{}",
+                    path_str.bold(),
+                    describe_broken_component(inspection)
+                );
+            }
+            PathState::NonEmptyDirectory(directory_counts) => {
+                print_non_empty_directory(writer, report, directory_counts)?;
+            }
+            PathState::EmptyDirectory => {
+                writeln!(
+                    writer,
+                    " {}  Path \"{}\" is an {}",
+                    CHECK_MARK.as_str().bold().green(),
+                    canonical_path_str.bold(),
+                    "empty directory".bold().green()
+                )?;
+
+                print_modified(writer, inspection)?;
+                print_mount_point(writer, inspection)?;
+            }
+            PathState::NonEmptyFile { bytes } => {
+                print_non_empty_file(writer, report, *bytes, self.peek)?;
+            }
+            PathState::EmptyFile => print_empty_file(writer, report)?,
+            PathState::NonEmptyStream { bytes } => {
+                writeln!(
+                    writer,
+                    " {}  {} is a {} (bytes: {})",
+                    X.as_str().bold().red(),
+                    stream_name(path_str),
+                    "non-empty stream".bold().red(),
+                    digits::group(*bytes).bold()
+                )?;
+            }
+            PathState::EmptyStream => {
+                writeln!(
+                    writer,
+                    " {}  {} is an {}",
+                    CHECK_MARK.as_str().bold().green(),
+                    stream_name(path_str),
+                    "empty stream".bold().green()
+                )?;
+            }
+            PathState::Symlink { .. } => print_symlink(writer, report)?,
+            PathState::SymlinkLoop { chain } => {
+                writeln!(
+                    writer,
+                    " {}  Path \"{}\" (non-canonicalized) is part of a {} ({})",
+                    X.as_str().bold().red(),
+                    path_str.bold(),
+                    "symbolic link loop".bold().red(),
+                    chain.join(" -> ")
+                )?;
+            }
+            PathState::DanglingSymlink {
+                target,
+                absolute_target,
+            } => {
+                writeln!(
+                    writer,
+                    " {}  Path \"{}\" (non-canonicalized) is a symbolic link to non-existent file \"{}\"{}",
+                    CHECK_MARK.as_str().bold().green(),
+                    path_str.bold(),
+                    target.bold(),
+                    describe_absolute_target(absolute_target.as_deref())
+                )?;
+
+                print_modified(writer, inspection)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn counts(
+        &self,
+        writer: &mut dyn Write,
+        directory_counts: &DirectoryCounts,
+    ) -> anyhow::Result<()> {
+        let DirectoryCounts {
+            directories,
+            files,
+            symlinks,
+            other,
+            total_items,
+            hidden,
+            unreadable,
+            vanished,
+            ..
+        } = directory_counts;
+
+        writeln!(
+            writer,
+            "directories={directories} files={files} symlinks={symlinks} other={other} total_items={total_items} hidden={hidden} unreadable={unreadable} vanished={vanished}"
+        )?;
+
+        Ok(())
+    }
+}
+
+struct Json {
+    lines: bool,
+}
+
+impl ReportFormatter for Json {
+    fn finished(
+        &self,
+        writer: &mut dyn Write,
+        report: &Report,
+        deletion: Deletion,
+    ) -> anyhow::Result<()> {
+        let value = result_value(report.inspection, report.warnings, deletion)?;
+
+        print_value(writer, &value, self.lines)?;
+
+        Ok(())
+    }
+
+    fn counts(
+        &self,
+        writer: &mut dyn Write,
+        directory_counts: &DirectoryCounts,
+    ) -> anyhow::Result<()> {
+        let mut value = serde_json::to_value(directory_counts)?;
+
+        schema::insert_version(&mut value);
+
+        writeln!(writer, "{}", serde_json::to_string(&value)?)?;
+
+        Ok(())
+    }
+}
+
+struct Sarif {
+    lines: bool,
+}
+
+impl ReportFormatter for Sarif {
+    fn finished(
+        &self,
+        writer: &mut dyn Write,
+        report: &Report,
+        _deletion: Deletion,
+    ) -> anyhow::Result<()> {
+        print_value(writer, &sarif::log(report.inspection), self.lines)?;
+
+        Ok(())
+    }
+
+    fn counts(
+        &self,
+        _writer: &mut dyn Write,
+        _directory_counts: &DirectoryCounts,
+    ) -> anyhow::Result<()> {
+        anyhow::bail!("SARIF output cannot report counts")
+    }
+}
+
+/// Without a header, so that the records of several runs (e.g. with --paths-from) can be concatenated
+struct Csv;
+
+impl ReportFormatter for Csv {
+    fn finished(
+        &self,
+        writer: &mut dyn Write,
+        report: &Report,
+        deletion: Deletion,
+    ) -> anyhow::Result<()> {
+        let deletion_value = serde_json::to_value(deletion)?;
+
+        let fields = [
+            report.canonical_path_str,
+            &report.inspection.state_name()?,
+            if report.inspection.is_empty() {
+                "true"
+            } else {
+                "false"
+            },
+            deletion_value
+                .as_str()
+                .context("Deletion outcome has no name")?,
+        ];
+
+        writeln!(writer, "{}", fields.map(csv_field).join(","))?;
+
+        Ok(())
+    }
+
+    fn counts(
+        &self,
+        writer: &mut dyn Write,
+        directory_counts: &DirectoryCounts,
+    ) -> anyhow::Result<()> {
+        let DirectoryCounts {
+            directories,
+            files,
+            symlinks,
+            other,
+            total_items,
+            hidden,
+            unreadable,
+            vanished,
+            ..
+        } = directory_counts;
+
+        writeln!(
+            writer,
+            "{directories},{files},{symlinks},{other},{total_items},{hidden},{unreadable},{vanished}"
+        )?;
+
+        Ok(())
+    }
+}
+
+struct Quiet;
+
+impl ReportFormatter for Quiet {
+    fn counts(
+        &self,
+        _writer: &mut dyn Write,
+        _directory_counts: &DirectoryCounts,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn print_value(
+    writer: &mut dyn Write,
+    value: &serde_json::Value,
+    lines: bool,
+) -> anyhow::Result<()> {
+    let string = if lines {
+        serde_json::to_string(value)?
+    } else {
+        serde_json::to_string_pretty(value)?
+    };
+
+    writeln!(writer, "{string}")?;
+
+    Ok(())
+}
+
+/// Quotes `field` if it contains a comma, quote, or line break, as RFC 4180 requires
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// The inspection as a JSON object, with "empty", "warnings", and `schema_version` added
+pub fn result_value(
+    inspection: &Inspection,
+    warnings: &[String],
+    deletion: Deletion,
+) -> anyhow::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(inspection)?;
+
+    if let serde_json::Value::Object(ma) = &mut value {
+        ma.insert("empty".to_owned(), inspection.is_empty().into());
+        ma.insert("deletion".to_owned(), serde_json::to_value(deletion)?);
+        ma.insert("warnings".to_owned(), warnings.into());
+    }
+
+    schema::insert_version(&mut value);
+
+    Ok(value)
+}
+
+/// Writes the result to `--output`, as SARIF or JSON depending on its extension
+pub fn write_output(path: &Path, report: &Report, deletion: Deletion) -> anyhow::Result<()> {
+    let value = match OutputFormat::from_extension(path) {
+        Some(OutputFormat::Sarif) => sarif::log(report.inspection),
+        _ => result_value(report.inspection, report.warnings, deletion)?,
+    };
+
+    let mut string = serde_json::to_string_pretty(&value)?;
+
+    string.push('\n');
+
+    file_system::write_atomically(path, string.as_bytes())
+}
+
+fn print_non_empty_directory(
+    writer: &mut dyn Write,
+    report: &Report,
+    directory_counts: &DirectoryCounts,
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        " {}  Path \"{}\" is a {} (directories: {}, files: {}, symlinks: {}, other: {}, total items: {}, of which hidden: {})",
+        X.as_str().bold().red(),
+        report.canonical_path_str.bold(),
+        "non-empty directory".bold().red(),
+        bold_if_greater_than_zero(directory_counts.directories),
+        bold_if_greater_than_zero(directory_counts.files),
+        bold_if_greater_than_zero(directory_counts.symlinks),
+        bold_if_greater_than_zero(directory_counts.other),
+        bold_if_greater_than_zero(directory_counts.total_items),
+        bold_if_greater_than_zero(directory_counts.hidden)
+    )?;
+
+    if directory_counts.unreadable > 0_u32 {
+        writeln!(
+            writer,
+            "    Unreadable entries: {} (their types could not be determined, so the counts by type are lower bounds)",
+            digits::group(directory_counts.unreadable).bold()
+        )?;
+    }
+
+    if directory_counts.vanished > 0_u32 {
+        writeln!(
+            writer,
+            "    Vanished entries: {} (deleted while being counted, so not counted)",
+            digits::group(directory_counts.vanished).bold()
+        )?;
+    }
+
+    if let Some(bt) = &directory_counts.extensions {
+        writeln!(
+            writer,
+            "    Files by extension: {}",
+            format_extension_counts(bt)
+        )?;
+    }
+
+    if let Some(re) = &directory_counts.recursive {
+        writeln!(
+            writer,
+            "    All descendants: directories: {}, files: {}, symlinks: {}, other: {}, bytes in files: {}{}",
+            digits::group(re.directories).bold(),
+            digits::group(re.files).bold(),
+            digits::group(re.symlinks).bold(),
+            digits::group(re.other).bold(),
+            digits::group(re.bytes).bold(),
+            if re.vanished > 0_u64 {
+                format!(", vanished: {}", digits::group(re.vanished).bold())
+            } else {
+                String::new()
+            }
+        )?;
+    }
+
+    if let Some(ve) = &directory_counts.depth_profile {
+        writeln!(writer, "    Entries by depth: {}", format_depth_profile(ve))?;
+    }
+
+    print_mount_point(writer, report.inspection)
+}
+
+fn print_non_empty_file(
+    writer: &mut dyn Write,
+    report: &Report,
+    bytes: u64,
+    peek: Option<u64>,
+) -> anyhow::Result<()> {
+    let inspection = report.inspection;
+
+    writeln!(
+        writer,
+        " {}  Path \"{}\" is a {} (bytes: {})",
+        X.as_str().bold().red(),
+        report.canonical_path_str.bold(),
+        "non-empty file".bold().red(),
+        digits::group(bytes).bold()
+    )?;
+
+    if let Some(fi) = &inspection.hash {
+        writeln!(writer, "    {}: {}", fi.algorithm.as_str(), fi.digest)?;
+    }
+
+    if let Some(ue) = peek {
+        for st in peek::lines(Path::new(&inspection.path), ue)? {
+            writeln!(writer, "    {}", st.dimmed())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn print_empty_file(writer: &mut dyn Write, report: &Report) -> io::Result<()> {
+    let inspection = report.inspection;
+
+    writeln!(
+        writer,
+        " {}  Path \"{}\" is an {}",
+        CHECK_MARK.as_str().bold().green(),
+        report.canonical_path_str.bold(),
+        "empty file".bold().green()
+    )?;
+
+    print_modified(writer, inspection)?;
+
+    if let Some(ue) = inspection.nul_bytes {
+        writeln!(
+            writer,
+            "    Contains only NUL bytes: {} bytes",
+            digits::group(ue).bold()
+        )?;
+    }
+
+    if let Some(se) = inspection.semantically_empty {
+        writeln!(
+            writer,
+            "    Semantically empty {}: {} bytes",
+            se.as_str(),
+            digits::group(
+                inspection
+                    .metadata
+                    .as_ref()
+                    .map_or(0_u64, fs::Metadata::len)
+            )
+            .bold()
+        )?;
+    }
+
+    if let Some(ue) = inspection.empty_when_bytes {
+        writeln!(
+            writer,
+            "    Counted as empty by --empty-when: {} bytes",
+            digits::group(ue).bold()
+        )?;
+    }
+
+    Ok(())
+}
+
+fn print_symlink(writer: &mut dyn Write, report: &Report) -> io::Result<()> {
+    let PathState::Symlink {
+        target,
+        absolute_target,
+        target_kind,
+        target_empty,
+        target_bytes,
+    } = &report.inspection.state
+    else {
+        return Ok(());
+    };
+
+    let emptiness = if *target_empty {
+        "an empty"
+    } else {
+        "a non-empty"
+    };
+
+    let resolved_description = match target_bytes {
+        Some(us) => format!("{emptiness} file (bytes: {})", digits::group(*us).bold()),
+        None => format!("{emptiness} directory"),
+    };
+
+    let target_description = match target_kind {
+        ItemKind::Symlink => {
+            format!("another symbolic link (ultimately resolving to {resolved_description})")
+        }
+        _ => resolved_description,
+    };
+
+    writeln!(
+        writer,
+        " {}  Path \"{}\" (non-canonicalized) is a symbolic link to \"{}\"{} (resolves to \"{}\"), which is {target_description}",
+        X.as_str().bold().red(),
+        report.path_str.bold(),
+        target.bold(),
+        describe_absolute_target(absolute_target.as_deref()),
+        report.canonical_path_str
+    )
+}
+
+fn print_mount_point(writer: &mut dyn Write, inspection: &Inspection) -> io::Result<()> {
+    if let Some(mo) = &inspection.mount_point {
+        writeln!(writer, "    Mount point: {}", describe_mount_point(mo))?;
+    }
+
+    if let Some(su) = &inspection.subvolume {
+        writeln!(writer, "    Subvolume: {}", su.description().bold())?;
+    }
+
+    Ok(())
+}
+
+/// Most common first, e.g. "40 .log, 3 .tmp, 1 (no extension)"
+fn format_extension_counts(extensions: &BTreeMap<String, u32>) -> String {
+    let mut extension_counts = extensions.iter().collect::<Vec<_>>();
+
+    extension_counts.sort_by(|(_, ua), (_, ub)| ub.cmp(ua));
+
+    extension_counts
+        .into_iter()
+        .map(|(st, ue)| {
+            if st.is_empty() {
+                format!("{} (no extension)", digits::group(*ue).bold())
+            } else {
+                format!("{} .{st}", digits::group(*ue).bold())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// E.g. "    Modified: 2024-10-15T08:30:00Z (3 days ago)", with `--show-mtime`
+fn print_modified(writer: &mut dyn Write, inspection: &Inspection) -> io::Result<()> {
+    let (Some(st), Some(me)) = (&inspection.modified, &inspection.metadata) else {
+        return Ok(());
+    };
+
+    let age = me
+        .modified()
+        .ok()
+        .and_then(|sy| SystemTime::now().duration_since(sy).ok())
+        .map(|du| format!(" ({})", describe_age(du)))
+        .unwrap_or_default();
+
+    writeln!(writer, "    Modified: {}{age}", st.bold())
+}
+
+/// E.g. "3 days ago", in the largest whole unit
+fn describe_age(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+
+    let (count, unit) = match seconds {
+        0_u64..60_u64 => (seconds, "second"),
+        60_u64..3_600_u64 => (seconds / 60_u64, "minute"),
+        3_600_u64..86_400_u64 => (seconds / 3_600_u64, "hour"),
+        _ => (seconds / 86_400_u64, "day"),
+    };
+
+    let plural = if count == 1_u64 { "" } else { "s" };
+
+    format!("{} {unit}{plural} ago", digits::group(count))
+}
+
+/// E.g. "1: 12, 2: 40, 3: 3", where depth 1 is the directory's own entries
+fn format_depth_profile(depth_profile: &[u64]) -> String {
+    depth_profile
+        .iter()
+        .enumerate()
+        .map(|(us, ue)| format!("{}: {}", us + 1_usize, digits::group(*ue).bold()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Shows where a relative symbolic link target points, as it is relative to the link's directory
+/// rather than the current directory
+fn describe_absolute_target(absolute_target: Option<&str>) -> String {
+    match absolute_target {
+        Some(st) => format!(" (relative to the link's directory: \"{}\")", st.bold()),
+        None => String::new(),
+    }
+}
+
+/// Names the component that stopped the path from resolving, unless it is the path itself
+fn describe_broken_component(inspection: &Inspection) -> String {
+    match &inspection.broken_component {
+        Some(br) if br.path != inspection.path || br.target.is_some() => format!(", because {br}"),
+        _ => String::new(),
+    }
+}
+
+/// E.g. "ext4 from \"/dev/sda1\"", or "bind mount of \"/srv\" from \"/dev/sda1\" (ext4)"
+fn describe_mount_point(mount_point: &MountPoint) -> String {
+    let source = mount_point.source.as_deref().unwrap_or("another device");
+
+    let file_system_type = mount_point
+        .file_system_type
+        .as_deref()
+        .unwrap_or("file system");
+
+    match &mount_point.bind_of {
+        Some(st) => format!(
+            "bind mount of \"{}\" from \"{}\" ({file_system_type})",
+            st.bold(),
+            source.bold()
+        ),
+        None => format!("{file_system_type} from \"{}\"", source.bold()),
+    }
+}
+
+fn stream_name(path_str: &str) -> String {
+    if path_str == "-" {
+        "Standard input".to_owned()
+    } else {
+        format!("Stream \"{}\"", path_str.bold())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspect::{self, InspectOptions};
+    use std::{env, path::PathBuf, process};
+
+    /// A fresh directory for one test, as tests run in parallel
+    fn test_directory(name: &str) -> PathBuf {
+        let path_buf = env::temp_dir().join(format!("empd-report-{}-{name}", process::id()));
+
+        let _ = fs::remove_dir_all(&path_buf);
+
+        fs::create_dir_all(&path_buf).unwrap();
+
+        path_buf
+    }
+
+    /// What `format` prints for `path_path`, without color codes
+    fn render(format: OutputFormat, lines: bool, path_path: &Path) -> String {
+        let path_str = path_path.to_str().unwrap();
+
+        let inspection = inspect::inspect(path_str, &InspectOptions::default()).unwrap();
+
+        let report = Report {
+            inspection: &inspection,
+            path_str,
+            canonical_path_str: inspection.canonical_path.as_deref().unwrap_or(path_str),
+            warnings: &[],
+        };
+
+        let formatter = format.formatter(None, lines);
+
+        let mut output = Vec::<u8>::new();
+
+        formatter.inspected(&mut output, &report).unwrap();
+        formatter
+            .finished(&mut output, &report, Deletion::NotAttempted)
+            .unwrap();
+
+        crate::strip_ansi_escapes(&String::from_utf8(output).unwrap())
+    }
+
+    fn render_counts(format: OutputFormat) -> String {
+        let directory_counts = DirectoryCounts {
+            directories: 1_u32,
+            files: 2_u32,
+            total_items: 3_u32,
+            hidden: 1_u32,
+            ..DirectoryCounts::default()
+        };
+
+        let mut output = Vec::<u8>::new();
+
+        format
+            .formatter(None, false)
+            .counts(&mut output, &directory_counts)
+            .unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn human_reports_empty_file() {
+        let directory = test_directory("human-empty-file");
+
+        let path_buf = directory.join("empty");
+
+        fs::write(&path_buf, b"").unwrap();
+
+        assert_eq!(
+            render(OutputFormat::Human, false, &path_buf),
+            format!(
+                " {}  Path \"{}\" is an empty file\n",
+                CHECK_MARK.as_str(),
+                path_buf.canonicalize().unwrap().display()
+            )
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn human_reports_non_empty_directory() {
+        let directory = test_directory("human-non-empty-directory");
+
+        fs::create_dir(directory.join("sub")).unwrap();
+        fs::write(directory.join(".hidden"), b"").unwrap();
+
+        assert_eq!(
+            render(OutputFormat::Human, false, &directory),
+            format!(
+                " {}  Path \"{}\" is a non-empty directory (directories: 1, files: 1, symlinks: 0, other: 0, total items: 2, of which hidden: 1)\n",
+                X.as_str(),
+                directory.canonicalize().unwrap().display()
+            )
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn json_is_pretty_unless_printing_lines() {
+        let directory = test_directory("json-lines");
+
+        let path_buf = directory.join("empty");
+
+        fs::write(&path_buf, b"").unwrap();
+
+        let pretty = render(OutputFormat::Json, false, &path_buf);
+        let line = render(OutputFormat::Json, true, &path_buf);
+
+        assert!(pretty.lines().count() > 1_usize);
+        assert_eq!(line.lines().count(), 1_usize);
+
+        let value = serde_json::from_str::<serde_json::Value>(&line).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::from_str::<serde_json::Value>(&pretty).unwrap()
+        );
+        assert_eq!(value["state"], "empty_file");
+        assert_eq!(value["empty"], true);
+        assert_eq!(value["deletion"], "not_attempted");
+        assert_eq!(value["schema_version"], schema::SCHEMA_VERSION);
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn csv_quotes_paths() {
+        let directory = test_directory("csv");
+
+        let path_buf = directory.join("a,b");
+
+        fs::write(&path_buf, b"x").unwrap();
+
+        assert_eq!(
+            render(OutputFormat::Csv, false, &path_buf),
+            format!(
+                "\"{}\",non_empty_file,false,not_attempted\n",
+                path_buf.canonicalize().unwrap().display()
+            )
+        );
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn quiet_prints_nothing() {
+        let directory = test_directory("quiet");
+
+        assert_eq!(render(OutputFormat::Quiet, false, &directory), "");
+        assert_eq!(render_counts(OutputFormat::Quiet), "");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn counts_are_printed_in_the_same_order() {
+        assert_eq!(
+            render_counts(OutputFormat::Human),
+            "directories=1 files=2 symlinks=0 other=0 total_items=3 hidden=1 unreadable=0 vanished=0\n"
+        );
+        assert_eq!(render_counts(OutputFormat::Csv), "1,2,0,0,3,1,0,0\n");
+    }
+
+    #[test]
+    fn csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -0,0 +1,291 @@
+//! Recreating what was deleted, from a `--backup` archive or an `--audit-log` journal
+
+use crate::{exit_code::ExitCode, inspect::ItemKind, output::status, signal};
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    ffi::CString,
+    fs::{self, OpenOptions, Permissions},
+    io::{self, ErrorKind},
+    os::unix::{
+        ffi::OsStrExt,
+        fs::{self as unix_fs, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+use tar::{Archive, EntryType};
+
+/// A deleted path, with whatever the archive or journal recorded about it
+struct RestoreItem {
+    path: PathBuf,
+    kind: ItemKind,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    /// Seconds since the Unix epoch
+    modified: Option<i64>,
+    /// For symbolic links
+    target: Option<PathBuf>,
+}
+
+/// The fields of an audit record that are needed to restore its path
+#[derive(Deserialize)]
+struct JournalRecord {
+    path: String,
+    canonical_path: Option<String>,
+    kind: ItemKind,
+    mode: Option<String>,
+    /// Missing from records written before ownership and modification times were recorded
+    owner_uid: Option<u32>,
+    owner_gid: Option<u32>,
+    mtime: Option<i64>,
+    target: Option<String>,
+}
+
+/// Recreates every path in `path` (a tar archive written with `--backup`, or an audit log), carrying
+/// on past paths that already exist and exiting with 1 if there were any
+pub fn restore(path: &Path) -> anyhow::Result<Result<(), ExitCode>> {
+    let bytes = fs::read(path).context("Could not read backup archive or audit log")?;
+
+    let restore_items = if bytes.get(257_usize..262_usize) == Some(b"ustar") {
+        archive_items(&bytes)?
+    } else {
+        journal_items(&bytes)?
+    };
+
+    let mut result = Ok(());
+
+    // Only these get the recorded metadata, so directories that were already there keep their own
+    let mut created_paths = HashSet::<PathBuf>::new();
+
+    // Parents were deleted after their children
+    for restore_item in restore_items.iter().rev() {
+        // What was restored so far still gets its modification time
+        if signal::received().is_some() {
+            result = Err(ExitCode::Interrupted);
+
+            break;
+        }
+
+        if !create(restore_item, &mut created_paths)? {
+            result = Err(ExitCode::Failure);
+        }
+    }
+
+    // After everything is created, as creating a directory's entries changes its modification time
+    for restore_item in restore_items
+        .iter()
+        .filter(|ri| created_paths.contains(&ri.path))
+    {
+        if let Some(it) = restore_item.modified {
+            set_modified(&restore_item.path, it).with_context(|| {
+                format!(
+                    "Could not set the modification time of \"{}\"",
+                    restore_item.path.display()
+                )
+            })?;
+        }
+    }
+
+    Ok(result)
+}
+
+fn archive_items(bytes: &[u8]) -> anyhow::Result<Vec<RestoreItem>> {
+    let mut archive = Archive::new(bytes);
+
+    let mut restore_items = Vec::<RestoreItem>::new();
+
+    for re in archive.entries().context("Could not read backup archive")? {
+        let entry = re.context("Could not read backup archive entry")?;
+
+        let header = entry.header();
+
+        let kind = match header.entry_type() {
+            EntryType::Directory => ItemKind::Directory,
+            EntryType::Regular => ItemKind::File,
+            EntryType::Symlink => ItemKind::Symlink,
+            en => anyhow::bail!("Backup archive contains an unexpected entry type ({en:?})"),
+        };
+
+        let mode = header.mode().ok();
+        let uid = header.uid().ok().and_then(|ue| u32::try_from(ue).ok());
+        let gid = header.gid().ok().and_then(|ue| u32::try_from(ue).ok());
+        let modified = header.mtime().ok().and_then(|ue| i64::try_from(ue).ok());
+
+        let target = entry.link_name()?.map(Cow::into_owned);
+
+        // Paths are stored relative to "/"
+        let path = Path::new("/").join(entry.path()?);
+
+        restore_items.push(RestoreItem {
+            path,
+            kind,
+            mode,
+            uid,
+            gid,
+            modified,
+            target,
+        });
+    }
+
+    Ok(restore_items)
+}
+
+/// Each non-blank line of a journal is one audit record
+fn journal_items(bytes: &[u8]) -> anyhow::Result<Vec<RestoreItem>> {
+    let journal = std::str::from_utf8(bytes).context("Audit log is not valid UTF-8")?;
+
+    journal
+        .lines()
+        .enumerate()
+        .filter(|(_, st)| !st.is_empty())
+        .map(|(us, st)| {
+            let journal_record = serde_json::from_str::<JournalRecord>(st).with_context(|| {
+                format!(
+                    "Line {} of the audit log is not an audit record",
+                    us + 1_usize
+                )
+            })?;
+
+            if journal_record.kind == ItemKind::Symlink && journal_record.target.is_none() {
+                anyhow::bail!(
+                    "Line {} of the audit log does not record the symbolic link's target",
+                    us + 1_usize
+                );
+            }
+
+            Ok(RestoreItem {
+                // Dangling symbolic links have no canonical path
+                path: PathBuf::from(journal_record.canonical_path.unwrap_or(journal_record.path)),
+                kind: journal_record.kind,
+                mode: journal_record
+                    .mode
+                    .and_then(|st| u32::from_str_radix(&st, 8_u32).ok()),
+                uid: journal_record.owner_uid,
+                gid: journal_record.owner_gid,
+                modified: journal_record.mtime,
+                target: journal_record.target.map(PathBuf::from),
+            })
+        })
+        .collect()
+}
+
+/// Returns `false` if something other than an existing directory was in the way. Adds what it
+/// creates, including missing parents, to `created_paths`.
+fn create(
+    restore_item: &RestoreItem,
+    created_paths: &mut HashSet<PathBuf>,
+) -> anyhow::Result<bool> {
+    let path = &restore_item.path;
+
+    if let Some(pa) = path.parent() {
+        let missing_ancestors = pa
+            .ancestors()
+            .take_while(|pa| fs::symlink_metadata(pa).is_err())
+            .map(Path::to_path_buf)
+            .collect::<Vec<_>>();
+
+        fs::create_dir_all(pa)
+            .with_context(|| format!("Could not create directory \"{}\"", pa.display()))?;
+
+        created_paths.extend(missing_ancestors);
+    }
+
+    let created = match restore_item.kind {
+        ItemKind::Directory => fs::create_dir(path),
+        ItemKind::File => OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .open(path)
+            .map(drop),
+        ItemKind::Symlink => unix_fs::symlink(
+            restore_item.target.as_deref().unwrap_or(Path::new("")),
+            path,
+        ),
+    };
+
+    // E.g. a parent that was created for one of its entries, whose metadata is still restored
+    let existed = match created {
+        Ok(()) => {
+            created_paths.insert(path.clone());
+
+            false
+        }
+        Err(er)
+            if er.kind() == ErrorKind::AlreadyExists
+                && restore_item.kind == ItemKind::Directory
+                && path.is_dir() =>
+        {
+            true
+        }
+        Err(er) if er.kind() == ErrorKind::AlreadyExists => {
+            status!(
+                "Not restoring \"{}\", as it already exists",
+                path.display().bold()
+            );
+
+            return Ok(false);
+        }
+        Err(er) => {
+            return Err(er).with_context(|| format!("Could not restore \"{}\"", path.display()));
+        }
+    };
+
+    if !created_paths.contains(path) {
+        return Ok(true);
+    }
+
+    // Symbolic links have no permissions of their own
+    if let (Some(ue), false) = (restore_item.mode, restore_item.kind == ItemKind::Symlink) {
+        fs::set_permissions(path, Permissions::from_mode(ue))?;
+    }
+
+    // Only root can give files away, so this is done where possible
+    if restore_item.uid.is_some() || restore_item.gid.is_some() {
+        match unix_fs::lchown(path, restore_item.uid, restore_item.gid) {
+            Err(er) if er.kind() != ErrorKind::PermissionDenied => return Err(er.into()),
+            _ => {}
+        }
+    }
+
+    if existed {
+        return Ok(true);
+    }
+
+    let description = match restore_item.kind {
+        ItemKind::Directory => "directory",
+        ItemKind::File => "file",
+        ItemKind::Symlink => "symbolic link",
+    };
+
+    status!("Restored {description} \"{}\"", path.display().bold());
+
+    Ok(true)
+}
+
+/// Sets the modification time without following symbolic links
+fn set_modified(path: &Path, modified: i64) -> anyhow::Result<()> {
+    let c_string = CString::new(path.as_os_str().as_bytes())?;
+
+    let mut times = unsafe { std::mem::zeroed::<[libc::timespec; 2_usize]>() };
+
+    times[0_usize].tv_nsec = libc::UTIME_OMIT;
+    times[1_usize].tv_sec = libc::time_t::try_from(modified)?;
+
+    if unsafe {
+        libc::utimensat(
+            libc::AT_FDCWD,
+            c_string.as_ptr(),
+            times.as_ptr(),
+            libc::AT_SYMLINK_NOFOLLOW,
+        )
+    } != 0_i32
+    {
+        return Err(io::Error::last_os_error().into());
+    }
+
+    Ok(())
+}
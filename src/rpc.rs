@@ -0,0 +1,267 @@
+//! JSON-RPC 2.0 over stdin/stdout, one request or response per line, so that tools can keep one empd
+//! process around instead of spawning one per path
+
+use crate::{
+    deletion::{Deletion, DeletionEngine},
+    exit_code::ExitCode,
+    inspect::{self, InspectOptions, Inspection},
+    output::{self, OutputStreams},
+    schema, signal,
+};
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+
+const PARSE_ERROR: i32 = -32_700_i32;
+const INVALID_REQUEST: i32 = -32_600_i32;
+const METHOD_NOT_FOUND: i32 = -32_601_i32;
+const INVALID_PARAMS: i32 = -32_602_i32;
+const INTERNAL_ERROR: i32 = -32_603_i32;
+/// In the range that JSON-RPC 2.0 reserves for implementation-defined server errors
+const DELETION_NOT_ENABLED: i32 = -32_000_i32;
+
+#[derive(Deserialize)]
+struct Request {
+    id: Option<Value>,
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct PathParams {
+    path: String,
+}
+
+struct Error {
+    code: i32,
+    message: String,
+}
+
+/// Serves requests until stdin is closed, refusing "delete" requests if there is no deletion engine
+pub fn serve(mut deletion_engine: Option<DeletionEngine>) -> anyhow::Result<Result<(), ExitCode>> {
+    // Stdout is for responses, so deletions are reported on stderr
+    output::set(OutputStreams::Stderr);
+
+    let mut stdout = io::stdout();
+
+    loop {
+        let Some(line) = signal::read_line()? else {
+            return Ok(Err(ExitCode::Interrupted));
+        };
+
+        // End of file
+        if line.is_empty() {
+            return Ok(Ok(()));
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        if let Some(response) = handle_line(&line, deletion_engine.as_mut()) {
+            let mut response_line = serde_json::to_string(&response)?;
+
+            response_line.push('\n');
+
+            stdout
+                .write_all(response_line.as_bytes())
+                .and_then(|()| stdout.flush())
+                .context("Could not write response to stdout")?;
+        }
+    }
+}
+
+fn handle_line(line: &str, deletion_engine: Option<&mut DeletionEngine>) -> Option<Value> {
+    let value = match serde_json::from_str::<Value>(line) {
+        Ok(va) => va,
+        Err(er) => {
+            return Some(error_response(
+                &Value::Null,
+                &Error {
+                    code: PARSE_ERROR,
+                    message: er.to_string(),
+                },
+            ));
+        }
+    };
+
+    // So that the client can tell which request was invalid, if it got that far
+    let id_or_null = value.get("id").cloned().unwrap_or(Value::Null);
+
+    let request = match serde_json::from_value::<Request>(value) {
+        Ok(re) if re.jsonrpc == "2.0" => re,
+        Ok(re) => {
+            return Some(error_response(
+                &id_or_null,
+                &Error {
+                    code: INVALID_REQUEST,
+                    message: format!(
+                        "Unsupported \"jsonrpc\" version \"{}\" (expected \"2.0\")",
+                        re.jsonrpc
+                    ),
+                },
+            ));
+        }
+        Err(er) => {
+            return Some(error_response(
+                &id_or_null,
+                &Error {
+                    code: INVALID_REQUEST,
+                    message: er.to_string(),
+                },
+            ));
+        }
+    };
+
+    let result = handle_request(&request.method, request.params, deletion_engine);
+
+    // Requests without an "id" are notifications, which never get a response
+    let id = request.id?;
+
+    let response = match result {
+        Ok(va) => json!({
+            "id": id,
+            "jsonrpc": "2.0",
+            "result": va,
+        }),
+        Err(er) => error_response(&id, &er),
+    };
+
+    Some(response)
+}
+
+fn handle_request(
+    method: &str,
+    params: Value,
+    deletion_engine: Option<&mut DeletionEngine>,
+) -> Result<Value, Error> {
+    match method {
+        "check" => {
+            let PathParams { path } = parse_params(params)?;
+
+            let inspection =
+                inspect::inspect(&path, &InspectOptions::default()).map_err(internal_error)?;
+
+            inspection_value(&inspection, None)
+        }
+        "delete" => {
+            let Some(deletion_engine) = deletion_engine else {
+                return Err(Error {
+                    code: DELETION_NOT_ENABLED,
+                    message:
+                        "Deleting is not enabled (start empd with --serve-stdio --delete-if-empty)"
+                            .to_owned(),
+                });
+            };
+
+            let PathParams { path } = parse_params(params)?;
+
+            let inspection =
+                inspect::inspect(&path, &InspectOptions::default()).map_err(internal_error)?;
+
+            let deletion = deletion_engine
+                .delete(&inspection, &path)
+                .map_err(internal_error)?;
+
+            inspection_value(&inspection, Some(deletion))
+        }
+        _ => Err(Error {
+            code: METHOD_NOT_FOUND,
+            message: format!("Unknown method \"{method}\" (supported methods: check, delete)"),
+        }),
+    }
+}
+
+fn parse_params(params: Value) -> Result<PathParams, Error> {
+    serde_json::from_value::<PathParams>(params).map_err(|er| Error {
+        code: INVALID_PARAMS,
+        message: er.to_string(),
+    })
+}
+
+fn inspection_value(inspection: &Inspection, deletion: Option<Deletion>) -> Result<Value, Error> {
+    let mut value = serde_json::to_value(inspection).map_err(internal_error)?;
+
+    if let Value::Object(ma) = &mut value {
+        ma.insert("empty".to_owned(), Value::Bool(inspection.is_empty()));
+
+        if let Some(de) = deletion {
+            ma.insert("deleted".to_owned(), Value::Bool(de == Deletion::Deleted));
+            ma.insert(
+                "deletion".to_owned(),
+                serde_json::to_value(de).map_err(internal_error)?,
+            );
+        }
+    }
+
+    schema::insert_version(&mut value);
+
+    Ok(value)
+}
+
+fn internal_error(error: impl Into<anyhow::Error>) -> Error {
+    Error {
+        code: INTERNAL_ERROR,
+        message: format!("{:#}", error.into()),
+    }
+}
+
+fn error_response(id: &Value, error: &Error) -> Value {
+    json!({
+        "error": {
+            "code": error.code,
+            "message": error.message,
+        },
+        "id": id,
+        "jsonrpc": "2.0",
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_to_delete_without_a_deletion_engine() {
+        let response = handle_line(
+            r#"{"id":7,"jsonrpc":"2.0","method":"delete","params":{"path":"/nonexistent"}}"#,
+            None,
+        );
+
+        assert_eq!(
+            response.unwrap()["error"]["code"],
+            json!(DELETION_NOT_ENABLED)
+        );
+    }
+
+    #[test]
+    fn echoes_the_id_of_an_invalid_request() {
+        let response = handle_line(r#"{"id":"a","method":"check"}"#, None).unwrap();
+
+        assert_eq!(response["id"], json!("a"));
+        assert_eq!(response["error"]["code"], json!(INVALID_REQUEST));
+    }
+
+    #[test]
+    fn rejects_other_jsonrpc_versions() {
+        let response = handle_line(
+            r#"{"id":1,"jsonrpc":"1.0","method":"check","params":{"path":"/"}}"#,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(response["id"], json!(1));
+        assert_eq!(response["error"]["code"], json!(INVALID_REQUEST));
+    }
+
+    #[test]
+    fn invalid_json_gets_a_null_id() {
+        let response = handle_line("{", None).unwrap();
+
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], json!(PARSE_ERROR));
+    }
+}
@@ -0,0 +1,126 @@
+//! SARIF 2.1.0 output, so that code scanning UIs (GitHub code scanning, Azure DevOps) can show
+//! empty paths inline
+
+use crate::inspect::{Inspection, PathState};
+use serde_json::{json, Value};
+use std::{fmt::Write, os::unix::ffi::OsStrExt, path::Path};
+
+/// (ID, description) of each kind of finding
+const RULES: [(&str, &str); 4_usize] = [
+    ("empty-directory", "Empty directory"),
+    ("empty-file", "Empty file"),
+    (
+        "dangling-symlink",
+        "Symbolic link to a path that does not exist",
+    ),
+    (
+        "empty-stream",
+        "Stream that ended without producing any bytes",
+    ),
+];
+
+pub fn log(inspection: &Inspection) -> Value {
+    let rule = match inspection.state {
+        PathState::EmptyDirectory => Some(RULES[0_usize]),
+        PathState::EmptyFile => Some(RULES[1_usize]),
+        PathState::DanglingSymlink { .. } => Some(RULES[2_usize]),
+        PathState::EmptyStream => Some(RULES[3_usize]),
+        _ => None,
+    };
+
+    let locations = inspection
+        .absolute_path()
+        .map(|st| {
+            json!({
+                "physicalLocation": {
+                    "artifactLocation": {
+                        "uri": file_uri(Path::new(&st)),
+                    },
+                },
+            })
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let results = rule
+        .map(|(id, description)| {
+            json!({
+                "level": "warning",
+                "locations": locations,
+                "message": {
+                    "text": format!("{description}: \"{}\"", inspection.path),
+                },
+                "ruleId": id,
+            })
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let rules = RULES
+        .iter()
+        .map(|(id, description)| {
+            json!({
+                "id": id,
+                "shortDescription": {
+                    "text": description,
+                },
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "runs": [
+            {
+                "invocations": [
+                    {
+                        "executionSuccessful": !matches!(
+                            inspection.state,
+                            PathState::NotFound | PathState::PermissionDenied
+                        ),
+                    },
+                ],
+                "results": results,
+                "tool": {
+                    "driver": {
+                        "informationUri": "https://github.com/andrewliebenow/empd",
+                        "name": "empd",
+                        "rules": rules,
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                },
+            },
+        ],
+        "version": "2.1.0",
+    })
+}
+
+/// A "file:" URI for the absolute path `path`, percent-encoding everything but unreserved characters
+/// and "/"
+fn file_uri(path: &Path) -> String {
+    let mut file_uri = "file://".to_owned();
+
+    for &ue in path.as_os_str().as_bytes() {
+        if ue.is_ascii_alphanumeric() || b"-._~/".contains(&ue) {
+            file_uri.push(char::from(ue));
+        } else {
+            let _ = write!(file_uri, "%{ue:02X}");
+        }
+    }
+
+    file_uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_uri_percent_encodes_reserved_characters() {
+        assert_eq!(file_uri(Path::new("/tmp/empty")), "file:///tmp/empty");
+        assert_eq!(
+            file_uri(Path::new("/tmp/a b/#1?\u{e9}")),
+            "file:///tmp/a%20b/%231%3F%C3%A9"
+        );
+    }
+}
@@ -0,0 +1,16 @@
+//! The version of the JSON that empd prints and writes, included as `schema_version` in every
+//! result, `--counts-only`, `version --json`, and `exit-codes --json` object, `--webhook` payload,
+//! `--serve-stdio` result, `--state` file, and `--audit-log` and `--plan` line
+//!
+//! Within one version, fields and values (e.g. new states or deletion outcomes) are only ever added.
+//! Removing or renaming a field, or changing its type or meaning, increments the version. SARIF
+//! output follows the SARIF 2.1.0 schema instead.
+
+pub const SCHEMA_VERSION: u32 = 1_u32;
+
+/// Adds `schema_version` to `value`, if it is an object
+pub fn insert_version(value: &mut serde_json::Value) {
+    if let serde_json::Value::Object(ma) = value {
+        ma.insert("schema_version".to_owned(), SCHEMA_VERSION.into());
+    }
+}
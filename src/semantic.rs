@@ -0,0 +1,119 @@
+//! Emptiness of structured files that have bytes but no data, e.g. "{}" or a CSV file with only a
+//! header, which data pipelines often leave behind
+
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+/// Files bigger than this are never parsed, as they cannot be empty documents in practice
+const MAX_BYTES: u64 = 1_048_576_u64;
+
+#[derive(Clone, Copy, Serialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SemanticFormat {
+    /// "{}", "[]", or only whitespace
+    Json,
+    /// Only "{}", "[]", "~", "null", document markers, directives, and comments
+    Yaml,
+    /// At most one non-blank line (the header)
+    Csv,
+}
+
+impl SemanticFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Json => "JSON",
+            Self::Yaml => "YAML",
+            Self::Csv => "CSV",
+        }
+    }
+
+    /// Whether the `len`-byte file at `path_path` is an empty document. Files that are too big, are
+    /// not UTF-8, or do not parse are not empty.
+    pub fn is_empty(self, path_path: &Path, len: u64) -> anyhow::Result<bool> {
+        if len > MAX_BYTES {
+            return Ok(false);
+        }
+
+        let bytes = fs::read(path_path).context("Could not read file")?;
+
+        let Ok(st) = std::str::from_utf8(&bytes) else {
+            return Ok(false);
+        };
+
+        let is_empty = match self {
+            Self::Json => is_empty_json(st),
+            Self::Yaml => is_empty_yaml(st),
+            Self::Csv => is_empty_csv(st),
+        };
+
+        Ok(is_empty)
+    }
+}
+
+fn is_empty_json(json: &str) -> bool {
+    if json.trim().is_empty() {
+        return true;
+    }
+
+    match serde_json::from_str::<serde_json::Value>(json) {
+        Ok(serde_json::Value::Object(ma)) => ma.is_empty(),
+        Ok(serde_json::Value::Array(ve)) => ve.is_empty(),
+        _ => false,
+    }
+}
+
+/// Looks at each line rather than parsing, so that no YAML parser is needed
+fn is_empty_yaml(yaml: &str) -> bool {
+    yaml.lines().all(|st| {
+        let st = st.trim();
+
+        // E.g. "--- {}"
+        let st = st.strip_prefix("---").map_or(st, str::trim_start);
+
+        st.is_empty()
+            || st.starts_with('#')
+            || st.starts_with('%')
+            || matches!(st, "..." | "{}" | "[]" | "~" | "null" | "Null" | "NULL")
+    })
+}
+
+fn is_empty_csv(csv: &str) -> bool {
+    csv.lines().filter(|st| !st.trim().is_empty()).count() <= 1_usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_empty_json_accepts_only_empty_documents() {
+        assert!(is_empty_json(""));
+        assert!(is_empty_json(" \n"));
+        assert!(is_empty_json("{}"));
+        assert!(is_empty_json("[ ]\n"));
+        assert!(!is_empty_json("{\"a\": 1}"));
+        assert!(!is_empty_json("[0]"));
+        assert!(!is_empty_json("null"));
+        assert!(!is_empty_json("{"));
+    }
+
+    #[test]
+    fn is_empty_yaml_accepts_only_empty_documents() {
+        assert!(is_empty_yaml(""));
+        assert!(is_empty_yaml("---\n# comment\n...\n"));
+        assert!(is_empty_yaml("%YAML 1.2\n--- {}\n"));
+        assert!(is_empty_yaml("~"));
+        assert!(!is_empty_yaml("a: 1"));
+        assert!(!is_empty_yaml("--- []\n- a\n"));
+    }
+
+    #[test]
+    fn is_empty_csv_accepts_at_most_a_header() {
+        assert!(is_empty_csv(""));
+        assert!(is_empty_csv("a,b\n"));
+        assert!(is_empty_csv("a,b\n\n  \n"));
+        assert!(!is_empty_csv("a,b\n1,2\n"));
+    }
+}
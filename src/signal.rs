@@ -0,0 +1,160 @@
+use anyhow::Context;
+use std::{
+    error, fmt,
+    io::{self, ErrorKind, Read},
+    ptr,
+    sync::atomic::{AtomicI32, Ordering},
+};
+
+static RECEIVED_SIGNAL: AtomicI32 = AtomicI32::new(0_i32);
+
+/// The error that work fails with when it stops early for SIGINT or SIGTERM, which exits with
+/// `ExitCode::Interrupted`
+#[derive(Debug)]
+pub struct Interrupted(pub &'static str);
+
+impl fmt::Display for Interrupted {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Interrupted by {}", self.0)
+    }
+}
+
+impl error::Error for Interrupted {}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    RECEIVED_SIGNAL.store(signal, Ordering::SeqCst);
+}
+
+/// Installs SIGINT and SIGTERM handlers that only record the signal, so that the run can stop at a
+/// point where nothing is half-done. A second signal terminates empd, in case it is stuck somewhere
+/// that does not check for the first.
+pub fn install_handlers() -> anyhow::Result<()> {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        let mut sigaction = unsafe { std::mem::zeroed::<libc::sigaction>() };
+
+        sigaction.sa_sigaction = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+
+        // No `SA_RESTART`, so that a blocking read of a confirmation prompt returns `EINTR`
+        sigaction.sa_flags = libc::SA_RESETHAND;
+
+        unsafe {
+            libc::sigemptyset(&raw mut sigaction.sa_mask);
+        }
+
+        if unsafe { libc::sigaction(signal, &raw const sigaction, ptr::null_mut()) } != 0_i32 {
+            return Err(io::Error::last_os_error()).context("Could not install signal handler");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn received() -> Option<&'static str> {
+    match RECEIVED_SIGNAL.load(Ordering::SeqCst) {
+        libc::SIGINT => Some("SIGINT"),
+        libc::SIGTERM => Some("SIGTERM"),
+        _ => None,
+    }
+}
+
+/// Fails with [`Interrupted`] if SIGINT or SIGTERM has been received
+pub fn check() -> anyhow::Result<()> {
+    match received() {
+        Some(st) => Err(Interrupted(st).into()),
+        None => Ok(()),
+    }
+}
+
+/// Reads `reader` in chunks, passing each to `chunk` until it returns `false` or the end is reached.
+/// Fails with [`Interrupted`] if SIGINT or SIGTERM is received, which `io::copy` and
+/// `Read::read_to_end` would carry on past.
+pub fn read_chunks(
+    mut reader: impl Read,
+    mut chunk: impl FnMut(&[u8]) -> bool,
+) -> anyhow::Result<()> {
+    let mut buffer = vec![0_u8; 64_usize * 1_024_usize];
+
+    loop {
+        check()?;
+
+        match reader.read(&mut buffer) {
+            Ok(0_usize) => {
+                return Ok(());
+            }
+            Ok(us) => {
+                if !chunk(&buffer[..us]) {
+                    return Ok(());
+                }
+            }
+            Err(er) if er.kind() == ErrorKind::Interrupted => {}
+            Err(er) => {
+                return Err(er.into());
+            }
+        }
+    }
+}
+
+/// Reads a line from stdin, returning `None` if SIGINT or SIGTERM is received first
+///
+/// `BufRead::read_line` retries on `EINTR`, so it would keep waiting for input after a signal.
+pub fn read_line() -> anyhow::Result<Option<String>> {
+    let mut stdin_lock = io::stdin().lock();
+
+    let mut line = Vec::<u8>::new();
+
+    loop {
+        if received().is_some() {
+            return Ok(None);
+        }
+
+        let mut buffer = [0_u8; 1_usize];
+
+        match stdin_lock.read(&mut buffer) {
+            Ok(0_usize) => {
+                break;
+            }
+            Ok(_) => {
+                line.push(buffer[0_usize]);
+
+                if buffer[0_usize] == b'\n' {
+                    break;
+                }
+            }
+            Err(er) if er.kind() == ErrorKind::Interrupted => {}
+            Err(er) => {
+                return Err(er).context("Could not read from stdin");
+            }
+        }
+    }
+
+    let string = String::from_utf8(line).context("Input was not valid UTF-8")?;
+
+    Ok(Some(string))
+}
+
+/// Reads `reader` to the end and returns how many bytes it produced, or `None` if SIGINT or SIGTERM
+/// is received first
+pub fn count_bytes(mut reader: impl Read) -> anyhow::Result<Option<u64>> {
+    let mut buffer = vec![0_u8; 64_usize * 1_024_usize];
+
+    let mut bytes = 0_u64;
+
+    loop {
+        if received().is_some() {
+            return Ok(None);
+        }
+
+        match reader.read(&mut buffer) {
+            Ok(0_usize) => {
+                return Ok(Some(bytes));
+            }
+            Ok(us) => {
+                bytes += u64::try_from(us)?;
+            }
+            Err(er) if er.kind() == ErrorKind::Interrupted => {}
+            Err(er) => {
+                return Err(er).context("Could not read stream");
+            }
+        }
+    }
+}
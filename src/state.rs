@@ -0,0 +1,88 @@
+use crate::{file_system, inspect::Inspection, output::note, schema};
+use anyhow::Context;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::Path};
+
+/// What the previous runs found, so that a run can report only what changed since then
+///
+/// Keyed by path, so several paths can share one state file.
+#[derive(Default, Deserialize, Serialize)]
+pub struct StateFile {
+    #[serde(skip_deserializing)]
+    schema_version: u32,
+    pub paths: BTreeMap<String, PathRecord>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PathRecord {
+    pub empty: bool,
+    /// The "state" of the inspection, e.g. `empty_file` or `non_empty_directory`
+    pub state: String,
+}
+
+impl StateFile {
+    /// An empty state if `path` does not exist yet
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(st) => serde_json::from_str::<Self>(&st)
+                .with_context(|| format!("Could not parse state file \"{}\"", path.display())),
+            Err(er) if er.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(er) => {
+                Err(er).with_context(|| format!("Could not read state file \"{}\"", path.display()))
+            }
+        }
+    }
+
+    fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let mut string = serde_json::to_string_pretty(self)?;
+
+        string.push('\n');
+
+        file_system::write_atomically(path, string.as_bytes())
+    }
+}
+
+/// Records `inspection` in the state file at `path`, reporting if the path became empty or stopped
+/// being empty since the previous run
+pub fn update(path: &Path, inspection: &Inspection) -> anyhow::Result<()> {
+    let mut state_file = StateFile::read(path)?;
+
+    let key = inspection
+        .absolute_path()
+        .unwrap_or_else(|| inspection.path.clone());
+
+    let path_record = PathRecord {
+        empty: inspection.is_empty(),
+        state: inspection.state_name()?,
+    };
+
+    match state_file.paths.get(&key) {
+        Some(pr) if pr.empty == path_record.empty => {}
+        Some(pr) if pr.empty => {
+            note!(
+                "Path \"{}\" {} since the last run (was {}, now {})",
+                key.bold(),
+                "stopped being empty".bold().red(),
+                pr.state,
+                path_record.state
+            );
+        }
+        previous => {
+            if path_record.empty {
+                note!(
+                    "Path \"{}\" {} since the last run (was {})",
+                    key.bold(),
+                    "became empty".bold().green(),
+                    previous.map_or("not recorded", |pr| pr.state.as_str())
+                );
+            }
+        }
+    }
+
+    state_file.schema_version = schema::SCHEMA_VERSION;
+
+    state_file.paths.insert(key, path_record);
+
+    state_file.write(path)
+}
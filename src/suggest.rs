@@ -0,0 +1,132 @@
+use std::path::{Component, Path, PathBuf};
+
+const MAX_SUGGESTIONS: usize = 3_usize;
+
+/// Paths like `path_str`, with its first missing component replaced by a similarly named sibling, so
+/// that a typo anywhere in a deep path can be corrected
+pub fn suggestions(path_str: &str) -> Vec<String> {
+    let path_path = Path::new(path_str);
+
+    let components = path_path.components().collect::<Vec<_>>();
+
+    let mut existing_path_buf = PathBuf::new();
+
+    for (us, co) in components.iter().enumerate() {
+        let candidate_path_buf = existing_path_buf.join(co);
+
+        if candidate_path_buf.symlink_metadata().is_ok() {
+            existing_path_buf = candidate_path_buf;
+
+            continue;
+        }
+
+        let Component::Normal(missing) = co else {
+            return Vec::new();
+        };
+
+        let missing = missing.to_string_lossy();
+
+        let parent_path = if existing_path_buf.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            existing_path_buf.as_path()
+        };
+
+        let Ok(read_dir) = parent_path.read_dir() else {
+            return Vec::new();
+        };
+
+        let max_distance = (missing.chars().count() / 3_usize).max(1_usize);
+
+        let rest = components[us + 1_usize..].iter().collect::<PathBuf>();
+
+        let mut siblings = read_dir
+            .filter_map(Result::ok)
+            .filter_map(|di| {
+                let name = di.file_name().to_string_lossy().into_owned();
+
+                let distance = edit_distance(&missing, &name);
+
+                let mut suggestion_path_buf = existing_path_buf.join(&name);
+
+                // Joining an empty path would add a trailing "/"
+                if !rest.as_os_str().is_empty() {
+                    suggestion_path_buf.push(&rest);
+                }
+
+                // Only suggest paths that exist, so the rest of the path has to match too
+                (distance <= max_distance && suggestion_path_buf.symlink_metadata().is_ok())
+                    .then_some((distance, name, suggestion_path_buf))
+            })
+            .collect::<Vec<_>>();
+
+        siblings.sort();
+
+        return siblings
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, _, pa)| pa.to_string_lossy().into_owned())
+            .collect();
+    }
+
+    Vec::new()
+}
+
+/// Edit distance where swapping two adjacent characters is one edit (optimal string alignment), as
+/// transpositions are the most common typo
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a_chars = a.chars().collect::<Vec<_>>();
+    let b_chars = b.chars().collect::<Vec<_>>();
+
+    // `rows[ua][ub]` is the distance between the first `ua` characters of `a` and the first `ub` of `b`
+    let mut rows = vec![vec![0_usize; b_chars.len() + 1_usize]; a_chars.len() + 1_usize];
+
+    for (ua, row) in rows.iter_mut().enumerate() {
+        row[0_usize] = ua;
+    }
+
+    for (ub, us) in rows[0_usize].iter_mut().enumerate() {
+        *us = ub;
+    }
+
+    for ua in 1_usize..=a_chars.len() {
+        for ub in 1_usize..=b_chars.len() {
+            let cost = usize::from(a_chars[ua - 1_usize] != b_chars[ub - 1_usize]);
+
+            let mut distance = (rows[ua - 1_usize][ub] + 1_usize)
+                .min(rows[ua][ub - 1_usize] + 1_usize)
+                .min(rows[ua - 1_usize][ub - 1_usize] + cost);
+
+            if ua > 1_usize
+                && ub > 1_usize
+                && a_chars[ua - 1_usize] == b_chars[ub - 2_usize]
+                && a_chars[ua - 2_usize] == b_chars[ub - 1_usize]
+            {
+                distance = distance.min(rows[ua - 2_usize][ub - 2_usize] + 1_usize);
+            }
+
+            rows[ua][ub] = distance;
+        }
+    }
+
+    rows[a_chars.len()][b_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_edits() {
+        assert_eq!(edit_distance("", ""), 0_usize);
+        assert_eq!(edit_distance("abc", "abc"), 0_usize);
+        assert_eq!(edit_distance("", "abc"), 3_usize);
+        assert_eq!(edit_distance("kitten", "sitting"), 3_usize);
+    }
+
+    #[test]
+    fn edit_distance_counts_a_transposition_as_one_edit() {
+        assert_eq!(edit_distance("ab", "ba"), 1_usize);
+        assert_eq!(edit_distance("recieve", "receive"), 1_usize);
+    }
+}
@@ -0,0 +1,82 @@
+#[cfg(any(feature = "notify", feature = "webhook"))]
+use crate::output::note;
+use crate::schema;
+#[cfg(feature = "webhook")]
+use crate::signal;
+use serde::Serialize;
+#[cfg(feature = "webhook")]
+use std::time::Duration;
+
+/// What a run found and did, for consumers that are not watching the terminal
+#[derive(Serialize)]
+pub struct RunSummary<'a> {
+    pub schema_version: u32,
+    pub path: &'a str,
+    pub empty_items_found: usize,
+    pub empty_items_deleted: usize,
+    pub exit_code: i32,
+    /// Included so that Slack incoming webhooks, which only render this field, show something useful
+    pub text: String,
+}
+
+impl<'a> RunSummary<'a> {
+    pub fn new(
+        path: &'a str,
+        empty_items_found: usize,
+        empty_items_deleted: usize,
+        exit_code: i32,
+    ) -> Self {
+        let text = format!(
+            "Checked \"{path}\": empty items found: {empty_items_found}, deleted: {empty_items_deleted}"
+        );
+
+        Self {
+            schema_version: schema::SCHEMA_VERSION,
+            path,
+            empty_items_found,
+            empty_items_deleted,
+            exit_code,
+            text,
+        }
+    }
+}
+
+// Scheduled jobs often have nothing to deliver notifications to, which should not fail the run, so
+// delivery errors are reported but not propagated
+
+#[cfg(feature = "notify")]
+pub fn send_desktop_notification(run_summary: &RunSummary) {
+    if let Err(er) = notify_rust::Notification::new()
+        .summary("empd")
+        .body(&run_summary.text)
+        .show()
+    {
+        note!("Could not send desktop notification: {er}");
+    }
+}
+
+#[cfg(feature = "webhook")]
+pub fn post_webhook(url: &str, run_summary: &RunSummary) {
+    // Posting can take as long as the timeout, which whoever sent the signal is not waiting for
+    if let Some(st) = signal::received() {
+        note!("Not posting summary to webhook, as empd was interrupted by {st}");
+
+        return;
+    }
+
+    let result = serde_json::to_string(run_summary)
+        .map_err(anyhow::Error::from)
+        .and_then(|st| {
+            ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(10_u64))
+                .build()
+                .post(url)
+                .set("Content-Type", "application/json")
+                .send_string(&st)
+                .map_err(anyhow::Error::from)
+        });
+
+    if let Err(er) = result {
+        note!("Could not post summary to webhook: {er}");
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{audit, exit_code::ExitCode, schema};
+use owo_colors::OwoColorize;
+use serde::Serialize;
+use std::time::{Duration, SystemTime};
+
+/// Cargo features, and whether this build has them enabled
+const FEATURES: [(&str, bool); 3_usize] = [
+    ("notify", cfg!(feature = "notify")),
+    ("otel", cfg!(feature = "otel")),
+    ("webhook", cfg!(feature = "webhook")),
+];
+
+#[derive(Serialize)]
+struct VersionInfo {
+    schema_version: u32,
+    version: &'static str,
+    /// Empty if the build was not from a Git checkout
+    git_commit: &'static str,
+    build_date: String,
+    target: &'static str,
+    features: Vec<&'static str>,
+}
+
+pub fn print(json: bool) -> anyhow::Result<Result<(), ExitCode>> {
+    let build_timestamp = env!("EMPD_BUILD_TIMESTAMP").parse::<u64>()?;
+
+    let version_info = VersionInfo {
+        schema_version: schema::SCHEMA_VERSION,
+        version: env!("CARGO_PKG_VERSION"),
+        git_commit: env!("EMPD_GIT_COMMIT"),
+        build_date: audit::format_timestamp(
+            SystemTime::UNIX_EPOCH + Duration::from_secs(build_timestamp),
+        )?,
+        target: env!("EMPD_TARGET"),
+        features: FEATURES
+            .iter()
+            .filter(|(_, bo)| *bo)
+            .map(|(st, _)| *st)
+            .collect(),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&version_info)?);
+
+        return Ok(Ok(()));
+    }
+
+    let VersionInfo {
+        version,
+        git_commit,
+        build_date,
+        target,
+        features,
+        ..
+    } = version_info;
+
+    println!("empd {}", version.bold());
+    println!(
+        "Commit: {}",
+        if git_commit.is_empty() {
+            "unknown"
+        } else {
+            git_commit
+        }
+    );
+    println!("Built: {build_date}");
+    println!("Target: {target}");
+    println!(
+        "Features: {}",
+        if features.is_empty() {
+            "none".to_owned()
+        } else {
+            features.join(", ")
+        }
+    );
+
+    Ok(Ok(()))
+}
@@ -0,0 +1,142 @@
+use crate::{
+    inspect::{self, InspectOptions, Inspection},
+    output::note,
+    signal,
+};
+use owo_colors::OwoColorize;
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long to sleep between checks for a signal, as `thread::sleep` does not return early on one
+const SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(100_u64);
+
+/// Polls `path_str` until it is empty or `timeout` elapses, returning the last inspection, or `None`
+/// if SIGINT or SIGTERM is received first
+pub fn wait_until_empty(
+    path_str: &str,
+    inspect_options: &InspectOptions,
+    timeout: Option<Duration>,
+    poll_interval: Duration,
+) -> anyhow::Result<Option<Inspection>> {
+    let start = Instant::now();
+
+    note!("Waiting for path \"{}\" to become empty", path_str.bold());
+
+    loop {
+        let inspection = inspect::inspect(path_str, inspect_options)?;
+
+        if inspection.is_empty() {
+            return Ok(Some(inspection));
+        }
+
+        if let Some(du) = timeout {
+            if start.elapsed() >= du {
+                note!(
+                    "Timed out after {} waiting for path \"{}\" to become empty",
+                    format_duration(du).bold(),
+                    path_str.bold()
+                );
+
+                return Ok(Some(inspection));
+            }
+        }
+
+        // `None` if the poll interval is too long to represent, in which case only a signal ends it
+        let sleep_until = Instant::now().checked_add(poll_interval);
+
+        while sleep_until.is_none_or(|it| Instant::now() < it) {
+            if signal::received().is_some() {
+                return Ok(None);
+            }
+
+            thread::sleep(sleep_until.map_or(SIGNAL_CHECK_INTERVAL, |it| {
+                SIGNAL_CHECK_INTERVAL.min(it.saturating_duration_since(Instant::now()))
+            }));
+        }
+    }
+}
+
+/// Parses durations like "500ms", "2s", "5m", and "1h" (a bare number is seconds)
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let split_at = input
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(input.len());
+
+    let (number, unit) = input.split_at(split_at);
+
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| format!("\"{input}\" does not start with a number"))?;
+
+    let seconds_per_unit = match unit {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "" | "s" => 1_u64,
+        "m" => 60_u64,
+        "h" => 3_600_u64,
+        _ => {
+            return Err(format!(
+                "Unknown unit \"{unit}\" in \"{input}\" (expected \"ms\", \"s\", \"m\", or \"h\")"
+            ));
+        }
+    };
+
+    let seconds = number
+        .checked_mul(seconds_per_unit)
+        .ok_or_else(|| format!("\"{input}\" is too long"))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Like [`parse_duration`], but not 0, which would re-inspect the path without pausing
+pub fn parse_poll_interval(input: &str) -> Result<Duration, String> {
+    let duration = parse_duration(input)?;
+
+    if duration.is_zero() {
+        return Err("The poll interval must be longer than 0".to_owned());
+    }
+
+    Ok(duration)
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.subsec_millis() == 0_u32 {
+        format!("{}s", duration.as_secs())
+    } else {
+        format!("{}ms", duration.as_millis())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_accepts_units() {
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500_u64)));
+        assert_eq!(parse_duration("2s"), Ok(Duration::from_secs(2_u64)));
+        assert_eq!(parse_duration("2"), Ok(Duration::from_secs(2_u64)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_mins(5_u64)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_hours(1_u64)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_unknown_units_and_missing_numbers() {
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("ms").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn parse_duration_rejects_overflow() {
+        assert!(parse_duration("999999999999999999h").is_err());
+    }
+
+    #[test]
+    fn parse_poll_interval_rejects_zero() {
+        assert!(parse_poll_interval("0").is_err());
+        assert!(parse_poll_interval("0ms").is_err());
+        assert_eq!(parse_poll_interval("1ms"), Ok(Duration::from_millis(1_u64)));
+    }
+}